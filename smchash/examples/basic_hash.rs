@@ -1,4 +1,5 @@
-use smchash::{SMCHash, hash_to_hex, Block};
+use smchash::{SMCHash, hash_to_hex, Block, Hash16, Transaction};
+use smchash::encoding::encode_address;
 
 fn main() {
     // Test basic hashing
@@ -6,8 +7,12 @@ fn main() {
     let hash = SMCHash::hash(data);
     println!("Hash of '{}': {}", std::str::from_utf8(data).unwrap(), hash_to_hex(&hash));
     
-    // Test creating a block
-    let block = Block::new([0; 16], data.to_vec(), 12345, 4);
-    println!("Block hash: {}", hash_to_hex(&block.hash));
+    // Addresses are shown in Base58Check so they can be copied by hand
+    println!("As an address: {}", encode_address(&hash));
+    
+    // Test creating a block, paying a coinbase to that address
+    let coinbase = Transaction::new([0; 16], hash, 5000, 0);
+    let block = Block::new(Hash16::ZERO, vec![coinbase], 12345, 4);
+    println!("Block hash: {}", block.hash());
     println!("Block valid: {}", block.validate(4));
-}
\ No newline at end of file
+}
@@ -0,0 +1,59 @@
+use smchash::{SMCHash, PROGRESS_INTERVAL, hash_to_hex};
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+// Difficulty used when none is given on the command line
+const DEFAULT_DIFFICULTY: u8 = 22;
+
+// How often the hashrate is printed
+const REPORT_EVERY: Duration = Duration::from_secs(1);
+
+fn main() {
+    // Usage: hashrate_monitor [DIFFICULTY [MAX_SECONDS]]
+    let mut args = std::env::args().skip(1);
+    let difficulty = match args.next() {
+        Some(arg) => arg.parse().expect("difficulty must be a number between 0 and 128"),
+        None => DEFAULT_DIFFICULTY,
+    };
+    let max_time = args.next().map(|arg| {
+        Duration::from_secs(arg.parse().expect("max seconds must be a whole number"))
+    });
+    let data = b"SMCHash hashrate monitor";
+    
+    println!("Mining at difficulty {} (expected ~{:.0} attempts)",
+             difficulty, smchash::expected_attempts(difficulty));
+    
+    let start = Instant::now();
+    let mut last_report = start;
+    
+    let report = |attempts: u64, hashes_per_sec: f64| {
+        let now = Instant::now();
+        if now.duration_since(last_report) >= REPORT_EVERY {
+            println!("[{:>6.1}s] {:>12} attempts, {:>10.0} H/s",
+                     start.elapsed().as_secs_f64(), attempts, hashes_per_sec);
+            last_report = now;
+        }
+        
+        // Give up on searches that run past the time limit
+        match max_time {
+            Some(max_time) if start.elapsed() >= max_time => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        }
+    };
+    let result =
+        SMCHash::create_proof_of_work_with_callback(data, difficulty, PROGRESS_INTERVAL, report);
+    
+    let total_time = start.elapsed();
+    let Some((nonce, hash)) = result else {
+        println!("\nNo solution within {:?}, aborted", total_time);
+        return;
+    };
+    let total_attempts = nonce + 1;
+    
+    println!("\nSolution found!");
+    println!("Nonce: {}", nonce);
+    println!("Hash: {}", hash_to_hex(&hash));
+    println!("Total attempts: {}", total_attempts);
+    println!("Total time: {:?}", total_time);
+    println!("Effective hashrate: {:.0} H/s", total_attempts as f64 / total_time.as_secs_f64());
+}
@@ -1,4 +1,4 @@
-use smchash::{SMCHash, Block, hash_to_hex};
+use smchash::{Block, Hash16, Transaction, select_transactions};
 use std::time::{Duration, Instant};
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -9,29 +9,10 @@ const TRANSACTION_COUNT_PER_BLOCK: usize = 10;  // Reduced transaction count
 const NUM_THREADS: usize = 2;  // Reduced thread count
 const DIFFICULTY: u8 = 4;      // Reduced difficulty for faster mining
 
-// Simple transaction structure
-#[derive(Clone)]
-struct Transaction {
-    from: [u8; 16],
-    to: [u8; 16],
-    amount: u64,
-    nonce: u64,
-}
-
-impl Transaction {
-    fn new(from: [u8; 16], to: [u8; 16], amount: u64, nonce: u64) -> Self {
-        Self { from, to, amount, nonce }
-    }
-    
-    fn serialize(&self) -> Vec<u8> {
-        let mut data = Vec::with_capacity(48);
-        data.extend_from_slice(&self.from);
-        data.extend_from_slice(&self.to);
-        data.extend_from_slice(&self.amount.to_le_bytes());
-        data.extend_from_slice(&self.nonce.to_le_bytes());
-        data
-    }
-}
+// Defaults for the reproducible CSV mode
+const DEFAULT_SEED: u64 = 42;
+const DEFAULT_CSV_BLOCKS: usize = 100;
+const CSV_GENESIS_TIMESTAMP: u64 = 1_700_000_000;
 
 // Extended Block for our benchmark
 struct BlockchainBlock {
@@ -41,15 +22,10 @@ struct BlockchainBlock {
 }
 
 impl BlockchainBlock {
-    fn new(prev_hash: [u8; 16], transactions: Vec<Transaction>, timestamp: u64, block_num: usize) -> Self {
-        // Serialize transactions
-        let mut tx_data = Vec::new();
-        for tx in &transactions {
-            tx_data.extend_from_slice(&tx.serialize());
-        }
-        
-        // Create block
-        let block = Block::new(prev_hash, tx_data, timestamp, DIFFICULTY);
+    fn new(prev_hash: Hash16, transactions: Vec<Transaction>, timestamp: u64, block_num: usize) -> Self {
+        // Create block, rejecting duplicate transactions
+        let block = Block::from_transactions(prev_hash, &transactions, timestamp, DIFFICULTY)
+            .expect("block contains duplicate transactions");
         
         Self {
             block,
@@ -59,7 +35,43 @@ impl BlockchainBlock {
     }
 }
 
+// Deterministic SplitMix64 generator so CSV runs are comparable across commits
+struct SeededRng(u64);
+
+impl SeededRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    
+    fn address(&mut self) -> [u8; 16] {
+        let mut address = [0u8; 16];
+        address[..8].copy_from_slice(&self.next_u64().to_le_bytes());
+        address[8..].copy_from_slice(&self.next_u64().to_le_bytes());
+        address
+    }
+}
+
+// Returns the value following `flag` on the command line, if present
+fn arg_value(args: &[String], flag: &str) -> Option<u64> {
+    let pos = args.iter().position(|a| a == flag)?;
+    let value = args.get(pos + 1).unwrap_or_else(|| panic!("{} requires a value", flag));
+    Some(value.parse().unwrap_or_else(|_| panic!("invalid value for {}: {}", flag, value)))
+}
+
 fn main() {
+    // Usage: mining_benchmark [--csv [--seed N] [--blocks N]]
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|a| a == "--csv") {
+        let seed = arg_value(&args, "--seed").unwrap_or(DEFAULT_SEED);
+        let blocks = arg_value(&args, "--blocks").map_or(DEFAULT_CSV_BLOCKS, |n| n as usize);
+        run_csv(seed, blocks);
+        return;
+    }
+    
     println!("Starting SMCHash Blockchain Mining Benchmark");
     println!("============================================");
     println!("Mining blocks with {} tx per block using {} threads", 
@@ -90,7 +102,6 @@ fn main() {
     
     // Mine blocks in parallel until time limit
     let mut handles = vec![];
-    let mut block_num = 1;
     
     for thread_id in 0..NUM_THREADS {
         let blockchain_clone = Arc::clone(&blockchain);
@@ -102,13 +113,11 @@ fn main() {
             let mut blocks_mined = 0;
             
             while *should_continue_clone.lock().unwrap() {
-                let mut prev_hash = [0u8; 16];
-                
                 // Get the last block's hash
-                {
+                let prev_hash = {
                     let chain = blockchain_clone.lock().unwrap();
-                    prev_hash = chain.last().unwrap().block.hash;
-                }
+                    chain.last().unwrap().block.hash()
+                };
                 
                 // Create transactions
                 let transactions = create_random_transactions(TRANSACTION_COUNT_PER_BLOCK);
@@ -122,6 +131,7 @@ fn main() {
                 let verification_start = Instant::now();
                 let is_valid = new_block.block.validate(DIFFICULTY);
                 let verification_time = verification_start.elapsed();
+                assert!(is_valid, "Thread {} mined an invalid block", thread_id);
                 
                 // Check if we should still continue
                 if !*should_continue_clone.lock().unwrap() {
@@ -162,7 +172,8 @@ fn main() {
     println!("================");
     let chain = blockchain.lock().unwrap();
     for (i, block) in chain.iter().enumerate() {
-        println!("Block {} - Hash: {}", i, hash_to_hex(&block.block.hash));
+        println!("Block {} (#{}, {} tx) - Hash: {}", i, block.block_num,
+                 block.transactions.len(), block.block.hash());
     }
     
     // Calculate average mining and verification times
@@ -177,16 +188,16 @@ fn main() {
     println!("Total time: {:?}", total_time);
     println!("Avg mining time: {:?} per block", avg_mining_time);
     println!("Avg verification time: {:?} per block", avg_verification_time);
-    println!("Blocks per second: {:.2}", BLOCK_COUNT as f64 / total_time.as_secs_f64());
+    println!("Blocks per second: {:.2}", total_blocks as f64 / total_time.as_secs_f64());
     println!("Transactions per second: {:.2}", 
-             (BLOCK_COUNT * TRANSACTION_COUNT_PER_BLOCK) as f64 / total_time.as_secs_f64());
+             (total_blocks * TRANSACTION_COUNT_PER_BLOCK) as f64 / total_time.as_secs_f64());
     
     // Revalidate the entire blockchain
     println!("\nRevalidating entire blockchain...");
     let validation_start = Instant::now();
     let mut is_valid = true;
     for i in 1..chain.len() {
-        let prev_hash = chain[i-1].block.hash;
+        let prev_hash = chain[i-1].block.hash();
         let current_block = &chain[i].block;
         
         // Validate block hash
@@ -197,7 +208,7 @@ fn main() {
         }
         
         // Validate block links
-        if current_block.prev_hash != prev_hash {
+        if current_block.header.prev_hash != prev_hash {
             println!("Block {} has invalid previous hash link!", i);
             is_valid = false;
             break;
@@ -211,8 +222,53 @@ fn main() {
              chain.len() as f64 / validation_time.as_secs_f64());
 }
 
+// Mines a fixed number of blocks on one thread from seeded transactions and
+// prints one CSV row per block
+fn run_csv(seed: u64, block_count: usize) {
+    let mut rng = SeededRng(seed);
+    
+    let genesis = BlockchainBlock::new(
+        Hash16::ZERO,
+        vec![Transaction::new([0u8; 16], rng.address(), 5000, 0)],
+        CSV_GENESIS_TIMESTAMP,
+        0,
+    );
+    let mut prev_hash = genesis.block.hash();
+    
+    println!("block_num,mining_us,verify_us,hash");
+    for block_num in 1..=block_count {
+        let transactions = create_seeded_transactions(&mut rng, TRANSACTION_COUNT_PER_BLOCK);
+        let timestamp = CSV_GENESIS_TIMESTAMP + block_num as u64;
+        
+        let mining_start = Instant::now();
+        let new_block = BlockchainBlock::new(prev_hash, transactions, timestamp, block_num);
+        let mining_time = mining_start.elapsed();
+        
+        let verification_start = Instant::now();
+        let is_valid = new_block.block.validate(DIFFICULTY);
+        let verification_time = verification_start.elapsed();
+        assert!(is_valid, "block {} failed validation", block_num);
+        
+        println!("{},{},{},{}", new_block.block_num, mining_time.as_micros(),
+                 verification_time.as_micros(), new_block.block.hash());
+        prev_hash = new_block.block.hash();
+    }
+}
+
+fn create_seeded_transactions(rng: &mut SeededRng, count: usize) -> Vec<Transaction> {
+    let mut transactions = Vec::with_capacity(count + 1);
+    transactions.push(Transaction::new([0u8; 16], rng.address(), 5000, 0));
+    
+    for i in 0..count {
+        let amount = (i as u64 + 1) * 100;
+        transactions.push(Transaction::new(rng.address(), rng.address(), amount, i as u64));
+    }
+    
+    transactions
+}
+
 fn create_genesis_block() -> BlockchainBlock {
-    let prev_hash = [0u8; 16];
+    let prev_hash = Hash16::ZERO;
     let timestamp = get_timestamp();
     
     BlockchainBlock::new(
@@ -223,27 +279,25 @@ fn create_genesis_block() -> BlockchainBlock {
     )
 }
 
-fn mine_block(prev_hash: [u8; 16], transactions: Vec<Transaction>, block_num: usize) -> BlockchainBlock {
+fn mine_block(prev_hash: Hash16, transactions: Vec<Transaction>, block_num: usize) -> BlockchainBlock {
     let timestamp = get_timestamp();
     BlockchainBlock::new(prev_hash, transactions, timestamp, block_num)
 }
 
 fn create_random_transactions(count: usize) -> Vec<Transaction> {
-    let mut transactions = Vec::with_capacity(count + 1);
-    
-    // Add a coinbase transaction first
-    transactions.push(create_coinbase_transaction());
-    
-    // Add regular transactions
-    for i in 0..count {
-        let from = generate_random_address();
-        let to = generate_random_address();
-        let amount = (i as u64 + 1) * 100;
-        let nonce = i as u64;
-        
-        transactions.push(Transaction::new(from, to, amount, nonce));
-    }
+    // Fill a mempool with more transactions than fit in the block
+    let pool: Vec<Transaction> = (0..count * 2)
+        .map(|i| {
+            let from = generate_random_address();
+            let to = generate_random_address();
+            let amount = (i as u64 + 1) * 100;
+            Transaction::new(from, to, amount, i as u64)
+        })
+        .collect();
     
+    // Coinbase first, then the highest-priority transactions
+    let mut transactions = vec![create_coinbase_transaction()];
+    transactions.extend(select_transactions(&pool, count + 1));
     transactions
 }
 
@@ -256,8 +310,8 @@ fn create_coinbase_transaction() -> Transaction {
 
 fn generate_random_address() -> [u8; 16] {
     let mut address = [0u8; 16];
-    for i in 0..16 {
-        address[i] = (std::time::SystemTime::now()
+    for byte in &mut address {
+        *byte = (std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_nanos() % 256) as u8;
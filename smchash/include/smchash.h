@@ -0,0 +1,90 @@
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#ifndef SMCHASH_H
+#define SMCHASH_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * SMCHash - A fast, lightweight hashing algorithm designed for blockchain applications
+ * Features:
+ * - Small hash size (16 bytes / 128 bits)
+ * - Fast verification
+ * - Lightweight computation
+ * - Designed for blockchain integration
+ */
+typedef struct SMCHash SMCHash;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Allocates a streaming hasher. Release it with `smchash_finalize` or
+ * `smchash_free`.
+ */
+struct SMCHash *smchash_new(void);
+
+/**
+ * Feeds `len` bytes at `data` into the hasher
+ *
+ * # Safety
+ *
+ * `hasher` must come from `smchash_new` and not have been released, and
+ * `data` must point to `len` readable bytes.
+ */
+void smchash_update(struct SMCHash *hasher, const uint8_t *data, size_t len);
+
+/**
+ * Writes the digest to `out` and releases the hasher
+ *
+ * # Safety
+ *
+ * `hasher` must come from `smchash_new` and not have been released, and
+ * `out` must point to 16 writable bytes.
+ */
+void smchash_finalize(struct SMCHash *hasher, uint8_t *out);
+
+/**
+ * Releases a hasher without finalizing it. Null is ignored.
+ *
+ * # Safety
+ *
+ * `hasher` must be null or come from `smchash_new` and not have been
+ * released.
+ */
+void smchash_free(struct SMCHash *hasher);
+
+/**
+ * One-shot hash of `len` bytes at `data`, written to `out`
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes and `out` to 16 writable bytes.
+ */
+void smchash_hash(const uint8_t *data, size_t len, uint8_t *out);
+
+/**
+ * Same as `SMCHash::verify_proof_of_work`: whether `nonce` gives
+ * `expected_hash` for the header at `data` and meets `difficulty`
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes and `expected_hash` to 16
+ * readable bytes.
+ */
+bool smchash_verify_pow(const uint8_t *data,
+                        size_t len,
+                        uint64_t nonce,
+                        uint8_t difficulty,
+                        const uint8_t *expected_hash);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SMCHASH_H */
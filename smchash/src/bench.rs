@@ -0,0 +1,105 @@
+//! Hashrate benchmark, so miners can calibrate difficulty and compare
+//! hardware with the same measurement
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! let rate = smchash::bench::measure_hashrate(Duration::from_secs(2));
+//! println!("{}", rate);
+//! ```
+
+use crate::{PowVerifier, SMCHash, MAX_DIFFICULTY, PROGRESS_INTERVAL};
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// Proof-of-work attempts hash a header-sized prefix plus the nonce
+const HEADER_LEN: usize = 80;
+
+// Bulk throughput is measured on inputs large enough that finalization
+// doesn't dominate
+const BULK_LEN: usize = 64 * 1024;
+
+/// Result of `measure_hashrate`, for a single thread
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hashrate {
+    /// Proof-of-work attempts per second on a block-header-sized input
+    pub hashes_per_sec: f64,
+    /// Bytes per second hashed with `SMCHash::hash` on large inputs
+    pub bytes_per_sec: f64,
+}
+
+impl Hashrate {
+    /// Bulk throughput in megabytes (10^6 bytes) per second
+    pub fn mb_per_sec(&self) -> f64 {
+        self.bytes_per_sec / 1e6
+    }
+}
+
+impl fmt::Display for Hashrate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.0} H/s, {:.1} MB/s", self.hashes_per_sec, self.mb_per_sec())
+    }
+}
+
+/// Measures single-threaded hashing speed for about `duration`: half of it
+/// searching nonces over an 80-byte header as `create_proof_of_work` does,
+/// half hashing 64 KiB inputs. Multiply `hashes_per_sec` by the thread
+/// count for an estimate of parallel mining speed.
+pub fn measure_hashrate(duration: Duration) -> Hashrate {
+    let half = duration / 2;
+    Hashrate {
+        hashes_per_sec: pow_rate(half),
+        bytes_per_sec: bulk_rate(half),
+    }
+}
+
+fn pow_rate(duration: Duration) -> f64 {
+    let verifier = PowVerifier::new(&[0x5a; HEADER_LEN]);
+    let start = Instant::now();
+    let mut attempts = 0u64;
+    
+    // Always at least one batch, so very short durations still measure something
+    loop {
+        // Practically never met, so every nonce in the batch is hashed
+        black_box(verifier.find(attempts..attempts + PROGRESS_INTERVAL, MAX_DIFFICULTY));
+        attempts += PROGRESS_INTERVAL;
+        if start.elapsed() >= duration {
+            break;
+        }
+    }
+    attempts as f64 / start.elapsed().as_secs_f64()
+}
+
+fn bulk_rate(duration: Duration) -> f64 {
+    let input = vec![0xa5; BULK_LEN];
+    let start = Instant::now();
+    let mut bytes = 0u64;
+    
+    loop {
+        black_box(SMCHash::hash(black_box(&input)));
+        bytes += BULK_LEN as u64;
+        if start.elapsed() >= duration {
+            break;
+        }
+    }
+    bytes as f64 / start.elapsed().as_secs_f64()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_measure_hashrate() {
+        let rate = measure_hashrate(Duration::from_millis(40));
+        assert!(rate.hashes_per_sec > 0.0 && rate.hashes_per_sec.is_finite());
+        assert!(rate.bytes_per_sec > 0.0 && rate.bytes_per_sec.is_finite());
+        assert_eq!(rate.mb_per_sec(), rate.bytes_per_sec / 1e6);
+        
+        // A zero duration still runs one round of each
+        let quick = measure_hashrate(Duration::ZERO);
+        assert!(quick.hashes_per_sec > 0.0);
+        assert!(quick.to_string().ends_with(" MB/s"));
+    }
+}
@@ -0,0 +1,147 @@
+// Command-line hasher with sha256sum-style output
+//
+// Usage: smchash [FILES...]          print "<hex>  <name>" for each file
+//        smchash --check MANIFEST    verify lines of a previous run
+//
+// With no files, or for "-", standard input is hashed. Exits with status 1
+// if any file cannot be read or fails the check.
+
+use smchash::{ct_eq, hash_to_hex, hex_to_hash, SMCHash};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: smchash [FILES...]\n       smchash --check MANIFEST";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    
+    let ok = match args.first().map(String::as_str) {
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            true
+        }
+        Some("-c" | "--check") => match &args[1..] {
+            [manifest] => check(manifest),
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::from(2);
+            }
+        },
+        _ if args.is_empty() => print_hashes(&["-".to_string()]),
+        _ => print_hashes(&args),
+    };
+    
+    if ok { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
+
+fn print_hashes(paths: &[String]) -> bool {
+    let mut ok = true;
+    for path in paths {
+        match hash_path(path) {
+            Ok(hash) => println!("{}  {}", hash_to_hex(&hash), path),
+            Err(e) => {
+                eprintln!("smchash: {}: {}", path, e);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+// Verifies every entry of a manifest, reporting each as OK or FAILED
+fn check(manifest: &str) -> bool {
+    let reader: Box<dyn BufRead> = if manifest == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        match File::open(manifest) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                eprintln!("smchash: {}: {}", manifest, e);
+                return false;
+            }
+        }
+    };
+    
+    let (mut failed, mut unreadable, mut malformed) = (0, 0, 0);
+    for (number, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("smchash: {}: {}", manifest, e);
+                return false;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        
+        let Some((expected, path)) = parse_manifest_line(&line) else {
+            eprintln!("smchash: {}: {}: improperly formatted line", manifest, number + 1);
+            malformed += 1;
+            continue;
+        };
+        match hash_path(path) {
+            Ok(hash) if ct_eq(&hash, &expected) => println!("{}: OK", path),
+            Ok(_) => {
+                println!("{}: FAILED", path);
+                failed += 1;
+            }
+            Err(e) => {
+                eprintln!("smchash: {}: {}", path, e);
+                println!("{}: FAILED open or read", path);
+                unreadable += 1;
+            }
+        }
+    }
+    
+    if malformed > 0 {
+        eprintln!("smchash: WARNING: {} line(s) improperly formatted", malformed);
+    }
+    if unreadable > 0 {
+        eprintln!("smchash: WARNING: {} listed file(s) could not be read", unreadable);
+    }
+    if failed > 0 {
+        eprintln!("smchash: WARNING: {} computed checksum(s) did NOT match", failed);
+    }
+    failed == 0 && unreadable == 0 && malformed == 0
+}
+
+// Splits "<32 hex digits>  <path>" (or " *<path>", the binary-mode marker of
+// the sha*sum tools) into the hash and path
+fn parse_manifest_line(line: &str) -> Option<([u8; 16], &str)> {
+    let (hex, rest) = line.split_once(' ')?;
+    let path = rest.strip_prefix(' ').or_else(|| rest.strip_prefix('*'))?;
+    if path.is_empty() {
+        return None;
+    }
+    Some((hex_to_hash(hex).ok()?, path))
+}
+
+fn hash_path(path: &str) -> io::Result<[u8; 16]> {
+    if path == "-" {
+        SMCHash::hash_reader(io::stdin().lock())
+    } else {
+        SMCHash::hash_reader(File::open(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_manifest_line() {
+        let hash = SMCHash::hash(b"snapshot");
+        let hex = hash_to_hex(&hash);
+        
+        assert_eq!(parse_manifest_line(&format!("{}  snap.bin", hex)), Some((hash, "snap.bin")));
+        assert_eq!(parse_manifest_line(&format!("{} *snap.bin", hex)), Some((hash, "snap.bin")));
+        assert_eq!(parse_manifest_line(&format!("{}  name with  spaces", hex)), Some((hash, "name with  spaces")));
+        
+        assert_eq!(parse_manifest_line(&format!("{} snap.bin", hex)), None);
+        assert_eq!(parse_manifest_line(&format!("{}  ", hex)), None);
+        assert_eq!(parse_manifest_line(&format!("{}  snap.bin", &hex[..30])), None);
+        assert_eq!(parse_manifest_line("snap.bin"), None);
+    }
+}
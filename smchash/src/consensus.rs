@@ -0,0 +1,6 @@
+//! Consensus rules shared by every node
+//!
+//! Functions here must give bit-identical results on every platform, so they
+//! use integer arithmetic only.
+
+pub mod difficulty;
@@ -0,0 +1,258 @@
+//! Difficulty retargeting: computes the target for the next block from the
+//! timestamps and targets of recent blocks, so block times stay near
+//! `Params::target_spacing` as hashrate changes
+//!
+//! Targets are numeric (see `meets_target`): lower is harder. Three
+//! algorithms are provided:
+//!
+//! - `moving_window`: the average target over a window, scaled by how far
+//!   the window's duration is from schedule (Bitcoin-style, clamped to 4x)
+//! - `lwma`: linearly weighted moving average of solve times, reacting faster
+//!   to recent blocks
+//! - `asert`: absolutely scheduled exponential retargeting, which depends
+//!   only on a fixed anchor block and the latest block
+
+/// Retargeting parameters, fixed for a chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Params {
+    /// Desired time between blocks, in the units of the block timestamps
+    pub target_spacing: u64,
+    /// Number of most recent solve times considered by `moving_window` and
+    /// `lwma`
+    pub window: usize,
+    /// Easiest target allowed (the "proof-of-work limit"); also the target
+    /// used until there is enough history
+    pub max_target: u128,
+}
+
+/// Timestamp and target of a past block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockTiming {
+    pub timestamp: u64,
+    pub target: u128,
+}
+
+/// Fixed reference block for `asert`, usually the block where ASERT was
+/// activated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Anchor {
+    pub height: u64,
+    /// Timestamp of the anchor block's parent
+    pub parent_timestamp: u64,
+    pub target: u128,
+}
+
+// Largest factor by which `moving_window` changes the target at once
+const MAX_ADJUSTMENT: u64 = 4;
+
+/// Next target from the last `params.window` solve times in `history`
+/// (oldest block first): the average target of those blocks, scaled by
+/// actual / expected duration, the duration clamped to within 4x of
+/// expected. Returns `params.max_target` with fewer than two blocks.
+pub fn moving_window(history: &[BlockTiming], params: &Params) -> u128 {
+    let blocks = recent(history, params.window);
+    if blocks.len() < 2 {
+        return params.max_target;
+    }
+    
+    let solves = (blocks.len() - 1) as u64;
+    let expected = params.target_spacing.saturating_mul(solves).max(1);
+    let actual = blocks[blocks.len() - 1]
+        .timestamp
+        .saturating_sub(blocks[0].timestamp)
+        .clamp(expected / MAX_ADJUSTMENT, expected.saturating_mul(MAX_ADJUSTMENT));
+    
+    clamp_target(mul_div(average_target(&blocks[1..]), actual, expected), params)
+}
+
+/// Next target by LWMA over the last `params.window` solve times in
+/// `history` (oldest block first). Solve time `i` (1 = oldest) is weighted
+/// by `i`, and each is clamped to `1..=6 * target_spacing` so out-of-order
+/// or manipulated timestamps have bounded effect. Returns
+/// `params.max_target` with fewer than two blocks.
+pub fn lwma(history: &[BlockTiming], params: &Params) -> u128 {
+    let blocks = recent(history, params.window);
+    if blocks.len() < 2 {
+        return params.max_target;
+    }
+    
+    let spacing = params.target_spacing.max(1);
+    let mut weighted: u64 = 0;
+    for (weight, pair) in (1u64..).zip(blocks.windows(2)) {
+        let solve_time = pair[1].timestamp.saturating_sub(pair[0].timestamp).clamp(1, spacing.saturating_mul(6));
+        weighted = weighted.saturating_add(weight.saturating_mul(solve_time));
+    }
+    
+    let solves = (blocks.len() - 1) as u64;
+    let expected = spacing.saturating_mul(solves.saturating_mul(solves + 1) / 2);
+    // Limits how fast the target can drop after a burst of quick blocks
+    let weighted = weighted.max(expected / 10);
+    
+    clamp_target(mul_div(average_target(&blocks[1..]), weighted, expected), params)
+}
+
+/// Next target by ASERT (as in aserti3-2d): the anchor's target, doubled for
+/// every `half_life` the chain is behind schedule and halved for every
+/// `half_life` ahead, with a fixed-point cubic approximation of `2^x` in
+/// between. `parent_height` and `parent_timestamp` describe the block the
+/// new one builds on.
+///
+/// Panics if `half_life` is zero.
+pub fn asert(anchor: &Anchor, parent_height: u64, parent_timestamp: u64, params: &Params, half_life: u64) -> u128 {
+    assert!(half_life > 0, "ASERT half-life must be non-zero");
+    
+    let time_delta = parent_timestamp as i128 - anchor.parent_timestamp as i128;
+    let height_delta = parent_height as i128 - anchor.height as i128;
+    // Saturating: once the exponent passes ±2^111 the target clamps anyway
+    let scheduled = (params.target_spacing as i128).saturating_mul(height_delta + 1);
+    let exponent = time_delta.saturating_sub(scheduled).saturating_mul(65536) / half_life as i128;
+    
+    // 2^exponent = 2^shifts * 2^(frac / 65536), with 0 <= frac < 65536
+    let shifts = exponent >> 16;
+    let frac = (exponent & 0xffff) as u128;
+    let factor = crate::exp2_q16(frac);
+    
+    let scaled = mul_div(anchor.target, factor as u64, 65536);
+    let shifted = if shifts >= 0 {
+        let shifts = shifts.min(128) as u32;
+        if shifts > 0 && scaled.leading_zeros() < shifts { u128::MAX } else { scaled << shifts }
+    } else {
+        scaled.checked_shr((-shifts).min(128) as u32).unwrap_or(0)
+    };
+    clamp_target(shifted, params)
+}
+
+// The last `window` solve times need the last `window + 1` blocks
+fn recent(history: &[BlockTiming], window: usize) -> &[BlockTiming] {
+    &history[history.len().saturating_sub(window.saturating_add(1))..]
+}
+
+// Mean of the targets, without overflowing on large ones
+fn average_target(blocks: &[BlockTiming]) -> u128 {
+    let count = blocks.len() as u128;
+    let quotients: u128 = blocks.iter().map(|b| b.target / count).sum();
+    let remainders: u128 = blocks.iter().map(|b| b.target % count).sum();
+    quotients + remainders / count
+}
+
+// `value * num / den` rounded down, saturating instead of overflowing
+fn mul_div(value: u128, num: u64, den: u64) -> u128 {
+    let (num, den) = (num as u128, den as u128);
+    match value.checked_mul(num) {
+        Some(product) => product / den,
+        // (value % den) * num < 2^128 as both factors are below 2^64
+        None => (value / den).saturating_mul(num).saturating_add((value % den) * num / den),
+    }
+}
+
+// Targets stay within 1..=max_target: a zero target could never be met
+fn clamp_target(target: u128, params: &Params) -> u128 {
+    target.clamp(1, params.max_target.max(1))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    
+    const PARAMS: Params = Params { target_spacing: 60, window: 10, max_target: u128::MAX >> 8 };
+    
+    // `count` blocks at the same target, `spacing` apart
+    fn steady(count: usize, spacing: u64, target: u128) -> Vec<BlockTiming> {
+        (0..count as u64).map(|i| BlockTiming { timestamp: 1_000_000 + i * spacing, target }).collect()
+    }
+    
+    #[test]
+    fn test_moving_window() {
+        let target = u128::MAX >> 20;
+        assert_eq!(moving_window(&steady(30, 60, target), &PARAMS), target);
+        
+        // Blocks twice as fast halve the target; twice as slow double it
+        assert_eq!(moving_window(&steady(30, 30, target), &PARAMS), target / 2);
+        assert_eq!(moving_window(&steady(30, 120, target), &PARAMS), target * 2);
+        
+        // Adjustments are clamped to 4x either way, and to the limit
+        assert_eq!(moving_window(&steady(30, 1, target), &PARAMS), target / 4);
+        assert_eq!(moving_window(&steady(30, 6000, target), &PARAMS), target * 4);
+        assert_eq!(moving_window(&steady(30, 6000, u128::MAX >> 9), &PARAMS), PARAMS.max_target);
+        
+        assert_eq!(moving_window(&steady(1, 60, target), &PARAMS), PARAMS.max_target);
+        assert_eq!(moving_window(&[], &PARAMS), PARAMS.max_target);
+    }
+    
+    #[test]
+    fn test_lwma() {
+        let target = u128::MAX >> 20;
+        assert_eq!(lwma(&steady(30, 60, target), &PARAMS), target);
+        assert_eq!(lwma(&steady(30, 30, target), &PARAMS), target / 2);
+        
+        // A recent slow block counts for more than an old one
+        let mut recent_slow = steady(11, 60, target);
+        let mut old_slow = recent_slow.clone();
+        for block in &mut recent_slow[10..] {
+            block.timestamp += 600;
+        }
+        for block in &mut old_slow[1..] {
+            block.timestamp += 600;
+        }
+        assert!(lwma(&recent_slow, &PARAMS) > lwma(&old_slow, &PARAMS));
+        assert!(lwma(&old_slow, &PARAMS) > target);
+        
+        // Timestamps going backwards count as the minimum solve time
+        let mut backwards = steady(11, 60, target);
+        backwards[10].timestamp = 0;
+        assert!(lwma(&backwards, &PARAMS) < target);
+        assert!(lwma(&backwards, &PARAMS) >= target / 10);
+    }
+    
+    #[test]
+    fn test_asert() {
+        let anchor = Anchor { height: 100, parent_timestamp: 1_000_000, target: u128::MAX >> 20 };
+        let half_life = 3600;
+        
+        // On schedule the target stays put
+        let on_schedule = 1_000_000 + 60 * 51;
+        assert_eq!(asert(&anchor, 150, on_schedule, &PARAMS, half_life), anchor.target);
+        
+        // One half-life behind doubles it, one ahead halves it
+        let doubled = asert(&anchor, 150, on_schedule + half_life, &PARAMS, half_life);
+        assert!(doubled.abs_diff(anchor.target * 2) <= anchor.target >> 40);
+        let halved = asert(&anchor, 150, on_schedule - half_life, &PARAMS, half_life);
+        assert!(halved.abs_diff(anchor.target / 2) <= anchor.target >> 40);
+        
+        // Half a half-life is about sqrt(2); the cubic is within 0.013%
+        let partial = asert(&anchor, 150, on_schedule + half_life / 2, &PARAMS, half_life);
+        let ratio = partial as f64 / anchor.target as f64;
+        assert!((ratio / std::f64::consts::SQRT_2 - 1.0).abs() < 1.3e-4, "{}", ratio);
+        
+        // Extremes clamp to the allowed range
+        assert_eq!(asert(&anchor, 150, u64::MAX, &PARAMS, half_life), PARAMS.max_target);
+        assert_eq!(asert(&anchor, u64::MAX, 0, &PARAMS, half_life), 1);
+    }
+    
+    #[test]
+    fn test_asert_saturates() {
+        // Huge spacings and height gaps must not overflow
+        let params = Params { target_spacing: u64::MAX, ..PARAMS };
+        let anchor = Anchor { height: 0, parent_timestamp: u64::MAX, target: u128::MAX >> 20 };
+        assert_eq!(asert(&anchor, u64::MAX, 0, &params, 1), 1);
+        let anchor = Anchor { height: u64::MAX, parent_timestamp: 0, ..anchor };
+        assert_eq!(asert(&anchor, 0, u64::MAX, &params, 1), params.max_target);
+        assert_eq!(asert(&anchor, 0, u64::MAX, &params, u64::MAX), params.max_target);
+    }
+    
+    #[test]
+    fn test_lwma_saturates() {
+        // Huge spacings and windows must not overflow
+        let params = Params { target_spacing: u64::MAX / 2, window: usize::MAX, ..PARAMS };
+        let target = u128::MAX >> 20;
+        assert!(lwma(&steady(30, u64::MAX / 64, target), &params) <= params.max_target);
+        assert!(lwma(&steady(3, 60, target), &params) >= 1);
+    }
+    
+    #[test]
+    fn test_mul_div_saturates() {
+        assert_eq!(mul_div(u128::MAX, 2, 4), u128::MAX / 2);
+        assert_eq!(mul_div(u128::MAX, 4, 2), u128::MAX);
+        assert_eq!(mul_div(10, 3, 4), 7);
+    }
+}
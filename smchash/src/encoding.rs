@@ -0,0 +1,167 @@
+//! Base58Check encoding for addresses and hashes
+//!
+//! Uses the Bitcoin alphabet, which leaves out look-alike characters (0, O,
+//! I, l). `encode_check` prefixes a version byte and appends a 4-byte
+//! SMCHash checksum, hashed under `DOMAIN_ADDRESS`, so a mistyped string
+//! is rejected instead of silently naming a different address.
+
+use crate::{constant_time_eq, SMCHash, SmcError, DOMAIN_ADDRESS};
+use alloc::{string::String, vec::Vec};
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Length of the checksum appended by `encode_check`
+pub const CHECKSUM_LEN: usize = 4;
+
+/// Version byte for wallet addresses
+pub const ADDRESS_VERSION: u8 = 0x3f;
+
+/// Plain Base58 of `data`. Each leading zero byte becomes a leading '1'.
+pub fn encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    
+    // Base-58 digits, least significant first
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for &byte in &data[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    
+    let mut encoded = String::with_capacity(zeros + digits.len());
+    encoded.extend(core::iter::repeat_n('1', zeros));
+    encoded.extend(digits.iter().rev().map(|&d| ALPHABET[d as usize] as char));
+    encoded
+}
+
+/// Inverse of `encode`. Fails with `InvalidBase58` on any character outside
+/// the alphabet.
+pub fn decode(encoded: &str) -> Result<Vec<u8>, SmcError> {
+    let zeros = encoded.bytes().take_while(|&c| c == b'1').count();
+    
+    // Bytes, least significant first
+    let mut bytes: Vec<u8> = Vec::with_capacity(encoded.len() * 733 / 1000 + 1);
+    for c in encoded[zeros..].chars() {
+        let mut carry = ALPHABET
+            .iter()
+            .position(|&a| a as char == c)
+            .ok_or(SmcError::InvalidBase58)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    
+    let mut decoded = alloc::vec![0u8; zeros];
+    decoded.extend(bytes.iter().rev());
+    Ok(decoded)
+}
+
+/// Base58 of `version || payload || checksum`, where the checksum is the
+/// first `CHECKSUM_LEN` bytes of `version || payload` hashed under
+/// `DOMAIN_ADDRESS`
+pub fn encode_check(version: u8, payload: &[u8]) -> String {
+    let mut data = Vec::with_capacity(1 + payload.len() + CHECKSUM_LEN);
+    data.push(version);
+    data.extend_from_slice(payload);
+    let checksum = SMCHash::hash_personalized(DOMAIN_ADDRESS, &data);
+    data.extend_from_slice(&checksum[..CHECKSUM_LEN]);
+    encode(&data)
+}
+
+/// Inverse of `encode_check`, returning the version byte and payload
+pub fn decode_check(encoded: &str) -> Result<(u8, Vec<u8>), SmcError> {
+    let mut data = decode(encoded)?;
+    if data.len() < 1 + CHECKSUM_LEN {
+        return Err(SmcError::DecodeTruncated);
+    }
+    
+    let checksum = data.split_off(data.len() - CHECKSUM_LEN);
+    if !constant_time_eq(&SMCHash::hash_personalized(DOMAIN_ADDRESS, &data)[..CHECKSUM_LEN], &checksum) {
+        return Err(SmcError::ChecksumMismatch);
+    }
+    let payload = data.split_off(1);
+    Ok((data[0], payload))
+}
+
+/// Base58Check form of a 16-byte wallet address
+pub fn encode_address(address: &[u8; 16]) -> String {
+    encode_check(ADDRESS_VERSION, address)
+}
+
+/// Parses an address produced by `encode_address`
+pub fn decode_address(encoded: &str) -> Result<[u8; 16], SmcError> {
+    let (version, payload) = decode_check(encoded)?;
+    if version != ADDRESS_VERSION {
+        return Err(SmcError::UnsupportedVersion(version.into()));
+    }
+    payload.try_into().map_err(|payload: Vec<u8>| {
+        if payload.len() < 16 {
+            SmcError::DecodeTruncated
+        } else {
+            SmcError::TrailingBytes
+        }
+    })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_base58_vectors() {
+        // Shared with every Base58 implementation using this alphabet
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(encode(&[0, 0, 0x28, 0x7f, 0xb4, 0xcd]), "11233QC4");
+        assert_eq!(decode("StV1DL6CwTryKyV").unwrap(), b"hello world");
+        assert_eq!(decode("11233QC4").unwrap(), [0, 0, 0x28, 0x7f, 0xb4, 0xcd]);
+        
+        for len in 0..40 {
+            let data: Vec<u8> = (0..len).map(|i| (i * 37 % 7) as u8).collect();
+            assert_eq!(decode(&encode(&data)).unwrap(), data);
+        }
+        
+        assert_eq!(decode("0OIl"), Err(SmcError::InvalidBase58));
+        assert_eq!(decode("abcé"), Err(SmcError::InvalidBase58));
+    }
+    
+    #[test]
+    fn test_base58_check() {
+        let address = SMCHash::hash(b"wallet");
+        let encoded = encode_address(&address);
+        assert_eq!(decode_address(&encoded), Ok(address));
+        assert_eq!(decode_check(&encoded).unwrap(), (ADDRESS_VERSION, address.to_vec()));
+        
+        // A single mistyped character is caught by the checksum
+        let mut typo: Vec<char> = encoded.chars().collect();
+        typo[5] = if typo[5] == 'z' { 'y' } else { 'z' };
+        let typo: String = typo.into_iter().collect();
+        assert_eq!(decode_address(&typo), Err(SmcError::ChecksumMismatch));
+        
+        assert_eq!(decode_check(&encode(&[1, 2, 3])), Err(SmcError::DecodeTruncated));
+        assert_eq!(decode_address(&encode_check(7, &address)), Err(SmcError::UnsupportedVersion(7)));
+        assert_eq!(decode_address(&encode_check(ADDRESS_VERSION, &address[..15])), Err(SmcError::DecodeTruncated));
+        assert_eq!(decode_address(&encode_check(ADDRESS_VERSION, &[0; 17])), Err(SmcError::TrailingBytes));
+        
+        // The checksum is tagged: one over the untagged hash is rejected
+        let mut untagged = vec![ADDRESS_VERSION];
+        untagged.extend_from_slice(&address);
+        let plain = SMCHash::hash(&untagged);
+        assert_ne!(plain[..CHECKSUM_LEN], SMCHash::hash_personalized(DOMAIN_ADDRESS, &untagged)[..CHECKSUM_LEN]);
+        untagged.extend_from_slice(&plain[..CHECKSUM_LEN]);
+        assert_eq!(decode_address(&encode(&untagged)), Err(SmcError::ChecksumMismatch));
+    }
+}
@@ -0,0 +1,151 @@
+//! C ABI for SMCHash, so C and C++ software (e.g. mining pools) can link the
+//! reference implementation instead of reimplementing it
+//!
+//! The declarations are in `include/smchash.h`, generated with
+//! `cbindgen --config cbindgen.toml --output include/smchash.h`. Build a
+//! linkable library with
+//! `cargo rustc --release --features ffi --crate-type cdylib` (or
+//! `staticlib`).
+//!
+//! Digests are written to caller-provided 16-byte buffers. `data` pointers
+//! may be null when the matching length is zero.
+
+use crate::SMCHash;
+use alloc::boxed::Box;
+
+/// Allocates a streaming hasher. Release it with `smchash_finalize` or
+/// `smchash_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn smchash_new() -> *mut SMCHash {
+    Box::into_raw(Box::new(SMCHash::new()))
+}
+
+/// Feeds `len` bytes at `data` into the hasher
+///
+/// # Safety
+///
+/// `hasher` must come from `smchash_new` and not have been released, and
+/// `data` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn smchash_update(hasher: *mut SMCHash, data: *const u8, len: usize) {
+    // SAFETY: guaranteed by the caller
+    unsafe { (*hasher).update(bytes(data, len)) };
+}
+
+/// Writes the digest to `out` and releases the hasher
+///
+/// # Safety
+///
+/// `hasher` must come from `smchash_new` and not have been released, and
+/// `out` must point to 16 writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn smchash_finalize(hasher: *mut SMCHash, out: *mut u8) {
+    // SAFETY: guaranteed by the caller
+    let hasher = unsafe { Box::from_raw(hasher) };
+    unsafe { write_digest(out, &hasher.finalize()) };
+}
+
+/// Releases a hasher without finalizing it. Null is ignored.
+///
+/// # Safety
+///
+/// `hasher` must be null or come from `smchash_new` and not have been
+/// released.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn smchash_free(hasher: *mut SMCHash) {
+    if !hasher.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { Box::from_raw(hasher) });
+    }
+}
+
+/// One-shot hash of `len` bytes at `data`, written to `out`
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `out` to 16 writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn smchash_hash(data: *const u8, len: usize, out: *mut u8) {
+    // SAFETY: guaranteed by the caller
+    unsafe { write_digest(out, &SMCHash::hash(bytes(data, len))) };
+}
+
+/// Same as `SMCHash::verify_proof_of_work`: whether `nonce` gives
+/// `expected_hash` for the header at `data` and meets `difficulty`
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `expected_hash` to 16
+/// readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn smchash_verify_pow(
+    data: *const u8,
+    len: usize,
+    nonce: u64,
+    difficulty: u8,
+    expected_hash: *const u8,
+) -> bool {
+    // SAFETY: guaranteed by the caller
+    let (data, expected) = unsafe { (bytes(data, len), &*expected_hash.cast::<[u8; 16]>()) };
+    SMCHash::verify_proof_of_work(data, nonce, difficulty, expected)
+}
+
+// Views a C buffer as a slice, allowing null for empty buffers
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        // SAFETY: the caller guarantees `len` readable bytes at `data`
+        unsafe { core::slice::from_raw_parts(data, len) }
+    }
+}
+
+unsafe fn write_digest(out: *mut u8, digest: &[u8; 16]) {
+    // SAFETY: the caller guarantees 16 writable bytes at `out`
+    unsafe { core::ptr::copy_nonoverlapping(digest.as_ptr(), out, 16) };
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use core::ptr;
+    
+    #[test]
+    fn test_ffi_matches_rust_api() {
+        let data = b"pool share header";
+        let mut out = [0u8; 16];
+        
+        unsafe {
+            smchash_hash(data.as_ptr(), data.len(), out.as_mut_ptr());
+            assert_eq!(out, SMCHash::hash(data));
+            
+            let hasher = smchash_new();
+            smchash_update(hasher, data.as_ptr(), 5);
+            smchash_update(hasher, ptr::null(), 0);
+            smchash_update(hasher, data[5..].as_ptr(), data.len() - 5);
+            let mut streamed = [0u8; 16];
+            smchash_finalize(hasher, streamed.as_mut_ptr());
+            assert_eq!(streamed, out);
+            
+            smchash_hash(ptr::null(), 0, out.as_mut_ptr());
+            assert_eq!(out, SMCHash::hash(b""));
+            
+            smchash_free(smchash_new());
+            smchash_free(ptr::null_mut());
+        }
+        
+        let (nonce, hash) = SMCHash::create_proof_of_work(data, 6);
+        unsafe {
+            assert!(smchash_verify_pow(data.as_ptr(), data.len(), nonce, 6, hash.as_ptr()));
+            assert!(!smchash_verify_pow(data.as_ptr(), data.len(), nonce + 1, 6, hash.as_ptr()));
+        }
+    }
+    
+    #[test]
+    fn test_header_declares_exports() {
+        let header = include_str!("../include/smchash.h");
+        for name in ["smchash_new", "smchash_update", "smchash_finalize", "smchash_free", "smchash_hash", "smchash_verify_pow"] {
+            assert!(header.contains(&format!("{}(", name)), "{} missing from header", name);
+        }
+    }
+}
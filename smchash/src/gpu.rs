@@ -0,0 +1,256 @@
+//! OpenCL mining backend
+//!
+//! `GpuMiner` runs the nonce search on the first OpenCL GPU found and
+//! returns the same solution as `SMCHash::create_proof_of_work` (the
+//! smallest valid nonce). The host hashes the data up to its last partial
+//! block once; each work item then appends its nonce, pads and compresses
+//! the final one or two blocks. Solutions are re-checked on the CPU before
+//! they are returned.
+//!
+//! Building with the `gpu` feature needs an OpenCL ICD loader
+//! (`libOpenCL`) to link against.
+
+use crate::{assert_difficulty_in_range, meets_difficulty, Midstate, MiningBackend};
+use opencl3::command_queue::CommandQueue;
+use opencl3::context::Context;
+use opencl3::device::{Device, CL_DEVICE_TYPE_GPU};
+use opencl3::error_codes::ClError;
+use opencl3::kernel::{ExecuteKernel, Kernel};
+use opencl3::memory::{Buffer, CL_MEM_READ_ONLY, CL_MEM_READ_WRITE};
+use opencl3::platform::get_platforms;
+use opencl3::program::Program;
+use opencl3::types::{cl_uint, cl_ulong, CL_BLOCKING};
+use std::fmt;
+use std::ptr;
+
+// Nonces tried per kernel launch
+const BATCH_SIZE: u64 = 1 << 22;
+
+// Mirrors `compress_block`, `step_word`, `step_constant` and `step_shift`
+// in lib.rs; the two must stay in sync
+const KERNEL_SOURCE: &str = r#"
+constant uint K[4] = {0x79cc4519, 0x9d8a7a87, 0xe9b5dba5, 0xc19bf274};
+constant uint S[4][4] = {{7, 12, 17, 22}, {5, 9, 14, 20}, {4, 11, 16, 23}, {6, 10, 15, 21}};
+
+void compress(uint state[4], const uchar *block) {
+    uint w[16];
+    for (int i = 0; i < 16; i++) {
+        w[i] = block[4 * i] | (block[4 * i + 1] << 8) | (block[4 * i + 2] << 16) | ((uint)block[4 * i + 3] << 24);
+    }
+    
+    uint a = state[0], b = state[1], c = state[2], d = state[3];
+    for (int round = 0; round < 4; round++) {
+        for (int i = 0; i < 16; i++) {
+            uint f, g;
+            switch (round) {
+                case 0: f = (b & c) | (~b & d); g = i; break;
+                case 1: f = (b & d) | (c & ~d); g = (5 * i + 1) % 16; break;
+                case 2: f = b ^ c ^ d; g = (3 * i + 5) % 16; break;
+                default: f = c ^ (b | ~d); g = (7 * i) % 16; break;
+            }
+            uint temp = d;
+            d = c;
+            c = b;
+            b = b + rotate(a + f + w[g] + K[round] + (uint)i, S[round][i % 4]);
+            a = temp;
+        }
+    }
+    state[0] += a;
+    state[1] += b;
+    state[2] += c;
+    state[3] += d;
+}
+
+// Writes the first nonce offset in this launch whose hash meets
+// `difficulty` leading zero bits to `found` (which starts at 0xffffffff)
+kernel void search(
+    global const uint *midstate,
+    global const uchar *tail,
+    uint tail_len,
+    ulong bit_len,
+    ulong base,
+    uint difficulty,
+    volatile global uint *found
+) {
+    uint offset = get_global_id(0);
+    ulong nonce = base + offset;
+    
+    uchar block[128];
+    for (int i = 0; i < 128; i++) {
+        block[i] = 0;
+    }
+    for (uint i = 0; i < tail_len; i++) {
+        block[i] = tail[i];
+    }
+    for (int i = 0; i < 8; i++) {
+        block[tail_len + i] = (uchar)(nonce >> (8 * i));
+    }
+    block[tail_len + 8] = 0x80;
+    uint blocks = tail_len + 8 < 56 ? 1 : 2;
+    for (int i = 0; i < 8; i++) {
+        block[blocks * 64 - 8 + i] = (uchar)(bit_len >> (8 * i));
+    }
+    
+    uint state[4] = {midstate[0], midstate[1], midstate[2], midstate[3]};
+    for (uint i = 0; i < blocks; i++) {
+        compress(state, block + 64 * i);
+    }
+    
+    // Digest bytes are the state words little-endian; count zero bits from
+    // the top of the first byte
+    uint zeros = 0;
+    for (int i = 0; i < 4; i++) {
+        uint word = state[i];
+        uint be = (word << 24) | ((word & 0xff00) << 8) | ((word >> 8) & 0xff00) | (word >> 24);
+        zeros += clz(be);
+        if (be != 0) {
+            break;
+        }
+    }
+    if (zeros >= difficulty) {
+        atomic_min(found, offset);
+    }
+}
+"#;
+
+/// Error setting up or running the OpenCL miner
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuError {
+    /// No OpenCL platform exposes a GPU
+    NoDevice,
+    /// The kernel failed to compile; holds the build log
+    Build(String),
+    /// An OpenCL call failed with this error code
+    OpenCl(i32),
+    /// The device reported a nonce that does not verify on the CPU
+    InvalidSolution(u64),
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GpuError::NoDevice => write!(f, "no OpenCL GPU found"),
+            GpuError::Build(log) => write!(f, "OpenCL kernel build failed: {}", log),
+            GpuError::OpenCl(code) => write!(f, "OpenCL error {}", code),
+            GpuError::InvalidSolution(nonce) => write!(f, "GPU returned invalid nonce {}", nonce),
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+impl From<ClError> for GpuError {
+    fn from(e: ClError) -> Self {
+        GpuError::OpenCl(e.0)
+    }
+}
+
+/// Proof-of-work miner running on an OpenCL GPU
+pub struct GpuMiner {
+    device_name: String,
+    context: Context,
+    queue: CommandQueue,
+    kernel: Kernel,
+}
+
+impl GpuMiner {
+    /// Compiles the kernel for the first GPU of the first platform that has
+    /// one
+    pub fn new() -> Result<Self, GpuError> {
+        let device_id = get_platforms()?
+            .iter()
+            .find_map(|platform| platform.get_devices(CL_DEVICE_TYPE_GPU).ok()?.first().copied())
+            .ok_or(GpuError::NoDevice)?;
+        let device = Device::new(device_id);
+        
+        let context = Context::from_device(&device)?;
+        let queue = CommandQueue::create(&context, device_id, 0)?;
+        let program = Program::create_and_build_from_source(&context, KERNEL_SOURCE, "").map_err(GpuError::Build)?;
+        let kernel = Kernel::create(&program, "search")?;
+        
+        Ok(GpuMiner { device_name: device.name()?, context, queue, kernel })
+    }
+    
+    /// Name reported by the OpenCL device
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+    
+    /// Same as `mine`, reporting device failures instead of panicking
+    pub fn try_mine(&self, data: &[u8], difficulty: u8) -> Result<(u64, [u8; 16]), GpuError> {
+        assert_difficulty_in_range(difficulty);
+        let midstate = Midstate::new(data);
+        let hasher = &midstate.hasher;
+        let bit_len: cl_ulong = hasher.total_bytes.wrapping_add(8).wrapping_mul(8);
+        let tail_len = hasher.buffer_len as cl_uint;
+        
+        let mut state = self.buffer::<cl_uint>(CL_MEM_READ_ONLY, 4)?;
+        let mut tail = self.buffer::<u8>(CL_MEM_READ_ONLY, 64)?;
+        let mut found = self.buffer::<cl_uint>(CL_MEM_READ_WRITE, 1)?;
+        self.queue.enqueue_write_buffer(&mut state, CL_BLOCKING, 0, &hasher.state, &[])?;
+        self.queue.enqueue_write_buffer(&mut tail, CL_BLOCKING, 0, &hasher.buffer, &[])?;
+        
+        let mut base: cl_ulong = 0;
+        while base < u64::MAX {
+            let count = BATCH_SIZE.min(u64::MAX - base);
+            let mut offset = [cl_uint::MAX];
+            self.queue.enqueue_write_buffer(&mut found, CL_BLOCKING, 0, &offset, &[])?;
+            ExecuteKernel::new(&self.kernel)
+                .set_arg(&state)
+                .set_arg(&tail)
+                .set_arg(&tail_len)
+                .set_arg(&bit_len)
+                .set_arg(&base)
+                .set_arg(&(difficulty as cl_uint))
+                .set_arg(&found)
+                .set_global_work_size(count as usize)
+                .enqueue_nd_range(&self.queue)?;
+            self.queue.enqueue_read_buffer(&found, CL_BLOCKING, 0, &mut offset, &[])?;
+            
+            if offset[0] != cl_uint::MAX {
+                let nonce = base + offset[0] as u64;
+                let hash = midstate.pow_hash(nonce);
+                if !meets_difficulty(&hash, difficulty) {
+                    return Err(GpuError::InvalidSolution(nonce));
+                }
+                return Ok((nonce, hash));
+            }
+            base += count;
+        }
+        panic!("no nonce meets difficulty {}", difficulty);
+    }
+    
+    fn buffer<T>(&self, flags: u64, count: usize) -> Result<Buffer<T>, GpuError> {
+        Ok(Buffer::create(&self.context, flags, count, ptr::null_mut())?)
+    }
+}
+
+impl MiningBackend for GpuMiner {
+    /// Panics if the device fails; see `try_mine`
+    fn mine(&self, data: &[u8], difficulty: u8) -> (u64, [u8; 16]) {
+        self.try_mine(data, difficulty).unwrap_or_else(|e| panic!("GPU mining failed: {}", e))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::SMCHash;
+    
+    #[test]
+    fn test_gpu_matches_cpu() {
+        // Machines without a GPU (such as CI) skip the comparison
+        let miner = match GpuMiner::new() {
+            Ok(miner) => miner,
+            Err(GpuError::NoDevice | GpuError::OpenCl(_)) => return,
+            Err(e) => panic!("{}", e),
+        };
+        
+        // Tails of both one and two final blocks
+        for data in [&b"short header"[..], &[7u8; 60][..], &[9u8; 150][..]] {
+            for difficulty in [0, 8, 16] {
+                assert_eq!(miner.mine(data, difficulty), SMCHash::create_proof_of_work(data, difficulty));
+            }
+        }
+    }
+}
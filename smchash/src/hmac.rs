@@ -0,0 +1,99 @@
+//! HMAC (RFC 2104) over SMCHash, for interop with systems that expect
+//! standard HMAC semantics rather than SMCHash's keyed mode
+
+use crate::{ct_eq, SMCHash};
+
+/// SMCHash compression block size in bytes
+pub const BLOCK_SIZE: usize = 64;
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+/// Streaming HMAC-SMCHash
+#[derive(Clone)]
+pub struct Hmac {
+    inner: SMCHash,
+    outer: SMCHash,
+}
+
+impl Hmac {
+    /// Creates an HMAC instance. Keys longer than the block size are hashed
+    /// first, shorter keys are zero-padded, as in RFC 2104.
+    pub fn new(key: &[u8]) -> Self {
+        let mut block_key = [0u8; BLOCK_SIZE];
+        if key.len() > BLOCK_SIZE {
+            block_key[..16].copy_from_slice(&SMCHash::hash(key));
+        } else {
+            block_key[..key.len()].copy_from_slice(key);
+        }
+        
+        let mut inner = SMCHash::new();
+        inner.update(&block_key.map(|b| b ^ IPAD));
+        let mut outer = SMCHash::new();
+        outer.update(&block_key.map(|b| b ^ OPAD));
+        
+        Hmac { inner, outer }
+    }
+    
+    pub fn update(&mut self, data: &[u8]) -> &mut Self {
+        self.inner.update(data);
+        self
+    }
+    
+    pub fn finalize(self) -> [u8; 16] {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+    
+    /// Checks the tag in constant time
+    pub fn verify(self, tag: &[u8; 16]) -> bool {
+        ct_eq(&self.finalize(), tag)
+    }
+}
+
+/// One-shot HMAC-SMCHash of `data` under `key`
+pub fn hmac(key: &[u8], data: &[u8]) -> [u8; 16] {
+    let mut mac = Hmac::new(key);
+    mac.update(data);
+    mac.finalize()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_hmac_matches_definition() {
+        let key = b"shared secret";
+        let data = b"what do ya want for nothing?";
+        
+        let mut padded = [0u8; BLOCK_SIZE];
+        padded[..key.len()].copy_from_slice(key);
+        let inner = SMCHash::hash(&[&padded.map(|b| b ^ IPAD)[..], data].concat());
+        let expected = SMCHash::hash(&[&padded.map(|b| b ^ OPAD)[..], &inner].concat());
+        
+        assert_eq!(hmac(key, data), expected);
+    }
+    
+    #[test]
+    fn test_hmac_streaming_and_long_keys() {
+        let data: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        let key = [0xaa; 131];
+        
+        let mut mac = Hmac::new(&key);
+        for chunk in data.chunks(37) {
+            mac.update(chunk);
+        }
+        let tag = mac.finalize();
+        assert_eq!(tag, hmac(&key, &data));
+        
+        // Over-long keys are replaced by their hash
+        assert_eq!(tag, hmac(&SMCHash::hash(&key), &data));
+        
+        let mut mac = Hmac::new(&key);
+        mac.update(&data);
+        assert!(mac.verify(&tag));
+        assert!(!Hmac::new(b"other key").update(&data).clone().verify(&tag));
+    }
+}
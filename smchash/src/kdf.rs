@@ -0,0 +1,78 @@
+//! HKDF (RFC 5869) over HMAC-SMCHash, for deriving session keys and child
+//! secrets from a master seed
+
+use crate::hmac::{hmac, Hmac};
+
+/// Longest output `expand` can produce: 255 HMAC blocks
+pub const MAX_OUTPUT_LEN: usize = 255 * 16;
+
+/// Concentrates the entropy of `ikm` (input keying material) into a 16-byte
+/// pseudorandom key. `salt` may be empty.
+pub fn extract(salt: &[u8], ikm: &[u8]) -> [u8; 16] {
+    hmac(salt, ikm)
+}
+
+/// Fills `out` with key material derived from `prk`. Different `info`
+/// contexts (e.g. "p2p session" and "wallet child 7") give independent keys
+/// from the same `prk`.
+///
+/// Panics if `out` is longer than `MAX_OUTPUT_LEN`.
+pub fn expand(prk: &[u8; 16], info: &[u8], out: &mut [u8]) {
+    assert!(out.len() <= MAX_OUTPUT_LEN, "HKDF output longer than 255 blocks");
+    let mut previous: Option<[u8; 16]> = None;
+    
+    for (counter, chunk) in (1..=255u8).zip(out.chunks_mut(16)) {
+        let mut mac = Hmac::new(prk);
+        if let Some(previous) = &previous {
+            mac.update(previous);
+        }
+        mac.update(info).update(&[counter]);
+        let block = mac.finalize();
+        
+        chunk.copy_from_slice(&block[..chunk.len()]);
+        previous = Some(block);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_expand_matches_definition() {
+        let prk = extract(b"salt", b"master seed");
+        let mut okm = [0u8; 40];
+        expand(&prk, b"context", &mut okm);
+        
+        let t1 = hmac(&prk, b"context\x01");
+        let t2 = hmac(&prk, &[&t1[..], b"context\x02"].concat());
+        let t3 = hmac(&prk, &[&t2[..], b"context\x03"].concat());
+        assert_eq!(okm[..16], t1);
+        assert_eq!(okm[16..32], t2);
+        assert_eq!(okm[32..], t3[..8]);
+        
+        // Shorter outputs are prefixes of longer ones
+        let mut short = [0u8; 20];
+        expand(&prk, b"context", &mut short);
+        assert_eq!(short[..], okm[..20]);
+    }
+    
+    #[test]
+    fn test_info_separates_keys() {
+        let prk = extract(&[], b"master seed");
+        let mut session = [0u8; 16];
+        let mut child = [0u8; 16];
+        expand(&prk, b"p2p session", &mut session);
+        expand(&prk, b"wallet child 0", &mut child);
+        assert_ne!(session, child);
+        
+        let mut longest = vec![0u8; MAX_OUTPUT_LEN];
+        expand(&prk, b"", &mut longest);
+    }
+    
+    #[test]
+    #[should_panic(expected = "255 blocks")]
+    fn test_expand_rejects_long_output() {
+        expand(&[0; 16], b"", &mut [0u8; MAX_OUTPUT_LEN + 1]);
+    }
+}
@@ -0,0 +1,213 @@
+//! Multi-lane compression: runs the compression function over `LANES`
+//! independent (state, block) pairs at once
+//!
+//! A single message cannot be sped up with SIMD, as every step of the
+//! compression function depends on the one before it. Independent messages
+//! can: the proof-of-work search hashes `LANES` nonces per call, one per
+//! vector lane. AVX2 on x86_64 is picked at runtime, NEON on aarch64 at
+//! compile time; other targets fall back to the portable `compress_block`
+//! one lane at a time.
+
+/// Number of messages compressed per call
+pub(crate) const LANES: usize = 8;
+
+pub(crate) fn compress(states: &mut [[u32; 4]; LANES], blocks: &[[u8; 64]; LANES]) {
+    #[cfg(target_arch = "x86_64")]
+    if avx2::available() {
+        // SAFETY: the CPU supports AVX2
+        unsafe { avx2::compress(states, blocks) };
+        return;
+    }
+    
+    // NEON is part of the aarch64 baseline, so it is selected at compile time
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+    neon::compress(states, blocks);
+    
+    #[cfg(not(all(target_arch = "aarch64", target_feature = "neon")))]
+    for (state, block) in states.iter_mut().zip(blocks) {
+        crate::compress_block(state, block);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use super::LANES;
+    use crate::{step_constant, step_shift, step_word};
+    use core::arch::x86_64::*;
+    
+    #[cfg(feature = "std")]
+    pub(super) fn available() -> bool {
+        std::is_x86_feature_detected!("avx2")
+    }
+    
+    // Without std there is no runtime detection; use the kernel only when
+    // the build already targets AVX2
+    #[cfg(not(feature = "std"))]
+    pub(super) fn available() -> bool {
+        cfg!(target_feature = "avx2")
+    }
+    
+    // One lane per 32-bit element: lane `l` works on `states[l]` and `blocks[l]`
+    #[target_feature(enable = "avx2")]
+    pub(super) fn compress(states: &mut [[u32; 4]; LANES], blocks: &[[u8; 64]; LANES]) {
+        let mut columns = [[0u32; LANES]; 16];
+        for (lane, block) in blocks.iter().enumerate() {
+            for (w, column) in columns.iter_mut().enumerate() {
+                column[lane] = u32::from_le_bytes(block[w * 4..w * 4 + 4].try_into().unwrap());
+            }
+        }
+        let mut words = [_mm256_setzero_si256(); 16];
+        for (word, column) in words.iter_mut().zip(&columns) {
+            *word = load(column);
+        }
+        
+        let mut initial = [_mm256_setzero_si256(); 4];
+        for (i, value) in initial.iter_mut().enumerate() {
+            *value = load(&states.map(|state| state[i]));
+        }
+        let [mut a, mut b, mut c, mut d] = initial;
+        let ones = _mm256_set1_epi32(-1);
+        
+        for round in 0..4 {
+            for i in 0..16 {
+                let f = match round {
+                    0 => _mm256_or_si256(_mm256_and_si256(b, c), _mm256_andnot_si256(b, d)),
+                    1 => _mm256_or_si256(_mm256_and_si256(b, d), _mm256_andnot_si256(d, c)),
+                    2 => _mm256_xor_si256(_mm256_xor_si256(b, c), d),
+                    _ => _mm256_xor_si256(c, _mm256_or_si256(b, _mm256_xor_si256(d, ones))),
+                };
+                
+                let temp = d;
+                d = c;
+                c = b;
+                let k = _mm256_set1_epi32(step_constant(round, i) as i32);
+                let sum = _mm256_add_epi32(_mm256_add_epi32(a, f), _mm256_add_epi32(words[step_word(round, i)], k));
+                b = _mm256_add_epi32(b, rotl(sum, step_shift(round, i)));
+                a = temp;
+            }
+        }
+        
+        for (i, value) in [a, b, c, d].into_iter().enumerate() {
+            let value = _mm256_add_epi32(initial[i], value);
+            let mut lanes = [0u32; LANES];
+            // SAFETY: `lanes` is 32 bytes, and storeu has no alignment requirement
+            unsafe { _mm256_storeu_si256(lanes.as_mut_ptr().cast(), value) };
+            for (state, lane) in states.iter_mut().zip(lanes) {
+                state[i] = lane;
+            }
+        }
+    }
+    
+    #[target_feature(enable = "avx2")]
+    fn load(lanes: &[u32; LANES]) -> __m256i {
+        // SAFETY: `lanes` is 32 bytes, and loadu has no alignment requirement
+        unsafe { _mm256_loadu_si256(lanes.as_ptr().cast()) }
+    }
+    
+    #[target_feature(enable = "avx2")]
+    fn rotl(x: __m256i, n: u32) -> __m256i {
+        let left = _mm256_sll_epi32(x, _mm_cvtsi32_si128(n as i32));
+        let right = _mm256_srl_epi32(x, _mm_cvtsi32_si128(32 - n as i32));
+        _mm256_or_si256(left, right)
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use super::LANES;
+    use crate::{step_constant, step_shift, step_word};
+    use core::arch::aarch64::*;
+    
+    // Lanes per 128-bit vector; the `LANES` messages are done in groups
+    const WIDTH: usize = 4;
+    
+    pub(super) fn compress(states: &mut [[u32; 4]; LANES], blocks: &[[u8; 64]; LANES]) {
+        for (states, blocks) in states.chunks_exact_mut(WIDTH).zip(blocks.chunks_exact(WIDTH)) {
+            compress_group(states.try_into().unwrap(), blocks.try_into().unwrap());
+        }
+    }
+    
+    // One lane per 32-bit element: lane `l` works on `states[l]` and `blocks[l]`
+    fn compress_group(states: &mut [[u32; 4]; WIDTH], blocks: &[[u8; 64]; WIDTH]) {
+        let mut columns = [[0u32; WIDTH]; 16];
+        for (lane, block) in blocks.iter().enumerate() {
+            for (w, column) in columns.iter_mut().enumerate() {
+                column[lane] = u32::from_le_bytes(block[w * 4..w * 4 + 4].try_into().unwrap());
+            }
+        }
+        let words = columns.map(|column| load(&column));
+        
+        let initial: [uint32x4_t; 4] = core::array::from_fn(|i| load(&states.map(|state| state[i])));
+        let [mut a, mut b, mut c, mut d] = initial;
+        
+        for round in 0..4 {
+            for i in 0..16 {
+                // vbicq(x, y) is x & !y, vornq(x, y) is x | !y
+                let f = match round {
+                    0 => vorrq_u32(vandq_u32(b, c), vbicq_u32(d, b)),
+                    1 => vorrq_u32(vandq_u32(b, d), vbicq_u32(c, d)),
+                    2 => veorq_u32(veorq_u32(b, c), d),
+                    _ => veorq_u32(c, vornq_u32(b, d)),
+                };
+                
+                let temp = d;
+                d = c;
+                c = b;
+                let k = vdupq_n_u32(step_constant(round, i));
+                let sum = vaddq_u32(vaddq_u32(a, f), vaddq_u32(words[step_word(round, i)], k));
+                b = vaddq_u32(b, rotl(sum, step_shift(round, i)));
+                a = temp;
+            }
+        }
+        
+        for (i, value) in [a, b, c, d].into_iter().enumerate() {
+            let lanes = store(vaddq_u32(initial[i], value));
+            for (state, lane) in states.iter_mut().zip(lanes) {
+                state[i] = lane;
+            }
+        }
+    }
+    
+    fn load(lanes: &[u32; WIDTH]) -> uint32x4_t {
+        // SAFETY: `lanes` holds exactly one vector's worth of u32s
+        unsafe { vld1q_u32(lanes.as_ptr()) }
+    }
+    
+    fn store(value: uint32x4_t) -> [u32; WIDTH] {
+        let mut lanes = [0u32; WIDTH];
+        // SAFETY: as in `load`
+        unsafe { vst1q_u32(lanes.as_mut_ptr(), value) };
+        lanes
+    }
+    
+    // vshlq shifts right for negative counts
+    fn rotl(x: uint32x4_t, n: u32) -> uint32x4_t {
+        let left = vshlq_u32(x, vdupq_n_s32(n as i32));
+        let right = vshlq_u32(x, vdupq_n_s32(n as i32 - 32));
+        vorrq_u32(left, right)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::compress_block;
+    
+    #[test]
+    fn test_lanes_match_scalar() {
+        let mut states = [[0u32; 4]; LANES];
+        let mut blocks = [[0u8; 64]; LANES];
+        for lane in 0..LANES {
+            states[lane] = core::array::from_fn(|i| ((lane * 4 + i) as u32).wrapping_mul(0x9e37_79b9));
+            blocks[lane] = core::array::from_fn(|i| (lane * 64 + i * 7) as u8);
+        }
+        
+        let mut expected = states;
+        for (state, block) in expected.iter_mut().zip(&blocks) {
+            compress_block(state, block);
+        }
+        
+        compress(&mut states, &blocks);
+        assert_eq!(states, expected);
+    }
+}
@@ -1,4 +1,93 @@
-use std::convert::TryInto;
+//! SMCHash: a small, fast 128-bit hash with proof-of-work helpers
+//!
+//! The core hasher, proof of work, HMAC and KDF work on `no_std` without an
+//! allocator. The `alloc` feature adds blocks, transactions, merkle trees and
+//! other heap-backed helpers; `std` (the default) adds I/O, threads and
+//! timing on top.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{
+    collections::BTreeSet,
+    format,
+    string::String,
+    vec,
+    vec::Vec,
+};
+use core::cmp::Ordering;
+use core::convert::TryInto;
+use core::fmt;
+use core::hash::{BuildHasher, Hasher};
+#[cfg(feature = "std")]
+use core::ops::ControlFlow;
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use lanes::LANES;
+#[cfg(feature = "std")]
+use std::collections::hash_map::Entry;
+#[cfg(feature = "std")]
+use std::collections::{BTreeSet, HashMap};
+#[cfg(feature = "mmap")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+#[cfg(feature = "mmap")]
+use std::path::Path;
+#[cfg(feature = "std")]
+use std::sync::atomic::AtomicU64;
+#[cfg(feature = "std")]
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+#[cfg(feature = "std")]
+use std::thread::{self, JoinHandle};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "std")]
+pub mod bench;
+pub mod consensus;
+#[cfg(feature = "alloc")]
+pub mod encoding;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod hmac;
+pub mod kdf;
+mod lanes;
+#[cfg(feature = "alloc")]
+pub mod pow;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "stratum")]
+pub mod stratum;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// Highest leading-zero-bit difficulty: every bit of a 16-byte hash is zero
+pub const MAX_DIFFICULTY: u8 = 128;
+
+/// Personalization tag for block headers, see `SMCHash::new_with_personalization`
+pub const DOMAIN_BLOCK_HEADER: &[u8] = b"smc/block-header";
+
+/// Personalization tag for transactions
+pub const DOMAIN_TRANSACTION: &[u8] = b"smc/transaction";
+
+/// Personalization tag for merkle tree nodes
+pub const DOMAIN_MERKLE_NODE: &[u8] = b"smc/merkle-node";
+
+/// Personalization tag for addresses
+pub const DOMAIN_ADDRESS: &[u8] = b"smc/address";
+
+/// Number of attempts between calls to a mining progress callback
+pub const PROGRESS_INTERVAL: u64 = 4096;
+
+// Size of the chunks read from `io::Read` sources
+#[cfg(feature = "std")]
+const READ_CHUNK_SIZE: usize = 64 * 1024;
 
 /// SMCHash - A fast, lightweight hashing algorithm designed for blockchain applications
 /// Features:
@@ -6,135 +95,337 @@ use std::convert::TryInto;
 /// - Fast verification
 /// - Lightweight computation
 /// - Designed for blockchain integration
-
+#[derive(Clone)]
 pub struct SMCHash {
     // Internal state variables
     state: [u32; 4],
-    buffer: Vec<u8>,
+    // Partial block waiting for more input; only `buffer[..buffer_len]` is live
+    buffer: [u8; 64],
+    buffer_len: usize,
     total_bytes: u64,
+    // State restored by `reset`: the IV, or the key-mixed state when keyed
+    initial_state: [u32; 4],
+}
+
+/// Snapshot of an `SMCHash` in progress, see `SMCHash::export_state`. With
+/// the `serde` feature it can be written out as a checkpoint and resumed
+/// after a restart.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SMCHashState {
+    pub state: [u32; 4],
+    pub buffer: Vec<u8>,
+    pub total_bytes: u64,
 }
 
 impl SMCHash {
+    // Initialize with prime numbers for better distribution
+    const IV: [u32; 4] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a];
+    
+    // Keyed mode starts from a different IV ("keyd" in the last word), so no
+    // keyed state can be reached by the unkeyed hash and vice versa
+    const KEYED_IV: [u32; 4] = [Self::IV[0], Self::IV[1], Self::IV[2], Self::IV[3] ^ 0x6b65_7964];
+    
+    // Same for seeded mode, with "seed"
+    const SEEDED_IV: [u32; 4] = [Self::IV[0], Self::IV[1], Self::IV[2], Self::IV[3] ^ 0x7365_6564];
+    
+    // And for personalized hashers, with "pers"
+    const PERSONAL_IV: [u32; 4] = [Self::IV[0], Self::IV[1], Self::IV[2], Self::IV[3] ^ 0x7065_7273];
+    
+    // And for salted hashers, with "salt"
+    const SALTED_IV: [u32; 4] = [Self::IV[0], Self::IV[1], Self::IV[2], Self::IV[3] ^ 0x7361_6c74];
+    
+    // Domain tag at the start of every XOF output block
+    const XOF_TAG: &'static [u8; 8] = b"SMCH-XOF";
+    
     /// Creates a new SMCHash instance with default initialization
     pub fn new() -> Self {
+        Self::with_initial_state(Self::IV)
+    }
+    
+    /// Creates a keyed hasher: the key, zero-padded to a full block, is
+    /// compressed into a domain-separated IV before any data. The resulting
+    /// digest alone is open to length extension like the plain hash; use
+    /// `mac` to authenticate messages.
+    pub fn new_keyed(key: &[u8; 16]) -> Self {
+        let mut key_block = [0u8; 64];
+        key_block[..16].copy_from_slice(key);
+        Self::with_prefix_block(Self::KEYED_IV, &key_block)
+    }
+    
+    /// Creates a hasher whose IV is perturbed by `seed`, so digests are
+    /// unpredictable without the seed. Use a random per-process seed to make
+    /// hash tables keyed by SMCHash resistant to hash flooding, or a per-node
+    /// seed to give each node its own (reproducible) transaction ordering.
+    ///
+    /// This is not a MAC: use `new_keyed` or `mac` when the seed is a secret
+    /// that authenticates data.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut seed_block = [0u8; 64];
+        seed_block[..8].copy_from_slice(&seed.to_le_bytes());
+        Self::with_prefix_block(Self::SEEDED_IV, &seed_block)
+    }
+    
+    /// Creates a hasher for the domain named by `tag` (e.g. one of the
+    /// `DOMAIN_*` constants). The tag's hash is compressed into a
+    /// domain-separated IV, so hashers with different tags, and the plain
+    /// hash, never share an internal state: a transaction blob cannot
+    /// collide with a block header blob however their bytes line up.
+    ///
+    /// Tags are public, unlike `new_keyed` keys. Tags of any length work,
+    /// though short fixed strings are the intended use.
+    pub fn new_with_personalization(tag: &[u8]) -> Self {
+        let mut tag_block = [0u8; 64];
+        tag_block[..16].copy_from_slice(&Self::hash(tag));
+        Self::with_prefix_block(Self::PERSONAL_IV, &tag_block)
+    }
+    
+    /// Streaming form of `hash_salted`, for fingerprinting data too large to
+    /// hold in memory
+    pub fn new_salted(salt: &[u8; 16]) -> Self {
+        let mut salt_block = [0u8; 64];
+        salt_block[..16].copy_from_slice(salt);
+        Self::with_prefix_block(Self::SALTED_IV, &salt_block)
+    }
+    
+    // Starts from `iv` and compresses `block` ahead of any data. `reset`
+    // returns to the state after the block.
+    fn with_prefix_block(iv: [u32; 4], block: &[u8; 64]) -> Self {
+        let mut hasher = Self::with_initial_state(iv);
+        hasher.process_block(block);
+        hasher.initial_state = hasher.state;
+        hasher
+    }
+    
+    fn with_initial_state(state: [u32; 4]) -> Self {
         SMCHash {
-            // Initialize with prime numbers for better distribution
-            state: [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a],
-            buffer: Vec::new(),
+            state,
+            buffer: [0; 64],
+            buffer_len: 0,
             total_bytes: 0,
+            initial_state: state,
         }
     }
     
+    /// Returns the internal state words. These only describe the message at
+    /// 64-byte block boundaries, as bytes in the buffer have not been mixed
+    /// in yet. The digest is these words serialized little-endian.
+    pub fn debug_state(&self) -> [u32; 4] {
+        self.state
+    }
+    
+    /// Creates a hasher resuming from a midstate at a block boundary: `state`
+    /// holds the state words after `total_bytes` bytes have been processed
+    ///
+    /// Panics if `total_bytes` is not a multiple of 64.
+    pub fn from_midstate(state: [u32; 4], total_bytes: u64) -> Self {
+        assert!(total_bytes.is_multiple_of(64), "midstate must be at a 64-byte block boundary");
+        Self::from_midstate_unchecked(state, total_bytes)
+    }
+    
+    fn from_midstate_unchecked(state: [u32; 4], total_bytes: u64) -> Self {
+        SMCHash {
+            state,
+            buffer: [0; 64],
+            buffer_len: 0,
+            total_bytes,
+            initial_state: Self::IV,
+        }
+    }
+
+    
+    /// Captures the current hasher state (compression state, buffered
+    /// partial block and length) so it can be restored with `from_state`
+    #[cfg(feature = "alloc")]
+    pub fn export_state(&self) -> SMCHashState {
+        SMCHashState {
+            state: self.state,
+            buffer: self.buffer[..self.buffer_len].to_vec(),
+            total_bytes: self.total_bytes,
+        }
+    }
+    
+    /// Restores a hasher from a state captured with `export_state`
+    ///
+    /// Panics if the state's buffer holds a full 64-byte block or more, which
+    /// `export_state` never produces.
+    #[cfg(feature = "alloc")]
+    pub fn from_state(state: SMCHashState) -> Self {
+        assert!(state.buffer.len() < 64, "SMCHashState buffer must hold less than one block");
+        let mut hasher = Self::from_midstate_unchecked(state.state, state.total_bytes);
+        hasher.buffer[..state.buffer.len()].copy_from_slice(&state.buffer);
+        hasher.buffer_len = state.buffer.len();
+        hasher
+    }
+    
     /// Updates the hash state with input data
     pub fn update(&mut self, data: &[u8]) {
-        self.buffer.extend_from_slice(data);
-        self.total_bytes += data.len() as u64;
+        debug_assert!(
+            self.total_bytes.checked_add(data.len() as u64).is_some(),
+            "SMCHash message length overflowed u64 bytes"
+        );
+        self.total_bytes = self.total_bytes.wrapping_add(data.len() as u64);
+        let mut data = data;
         
-        // Process complete blocks (64 bytes each)
-        while self.buffer.len() >= 64 {
-            // Create a separate buffer to avoid borrowing issues
-            let block_data = self.buffer[0..64].to_vec();
+        // Top up a partially filled buffer first
+        if self.buffer_len > 0 {
+            let take = (64 - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+            
+            if self.buffer_len < 64 {
+                return;
+            }
+            
+            // Copy the block out to avoid borrowing issues
+            let block_data = self.buffer;
             self.process_block(&block_data);
-            self.buffer.drain(0..64);
+            self.buffer_len = 0;
+        }
+        
+        // Process complete blocks (64 bytes each) straight from the input
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            self.process_block(block);
         }
+        
+        // Only the trailing partial block is copied
+        let remainder = blocks.remainder();
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffer_len = remainder.len();
     }
     
-    /// Processes a single 64-byte block
-    fn process_block(&mut self, block: &[u8]) {
-        // Convert block to sixteen 32-bit words
-        let mut words = [0u32; 16];
-        for i in 0..16 {
-            let start = i * 4;
-            let word_bytes: [u8; 4] = block[start..start + 4].try_into().unwrap();
-            words[i] = u32::from_le_bytes(word_bytes);
-        }
-        
-        // Save current state
-        let mut a = self.state[0];
-        let mut b = self.state[1];
-        let mut c = self.state[2];
-        let mut d = self.state[3];
-        
-        // Main mixing function - 4 rounds of operations
-        for round in 0..4 {
-            for i in 0..16 {
-                let f = match round {
-                    0 => (b & c) | (!b & d),             // Round 1: if b then c else d
-                    1 => (b & d) | (c & !d),             // Round 2: different bit mixing
-                    2 => b ^ c ^ d,                     // Round 3: XOR
-                    _ => c ^ (b | !d),                  // Round 4: alternative mixing
-                };
-                
-                let word_idx = match round {
-                    0 => i,                            // Sequential in first round
-                    1 => (5*i + 1) % 16,               // Different permutation per round
-                    2 => (3*i + 5) % 16,
-                    _ => (7*i) % 16,
-                };
-                
-                let k = match round {
-                    0 => 0x79cc4519,
-                    1 => 0x9d8a7a87,
-                    2 => 0xe9b5dba5,
-                    _ => 0xc19bf274,
-                } + i as u32;
-                
-                // Rotation constants
-                let s = match round {
-                    0 => [7, 12, 17, 22],
-                    1 => [5, 9, 14, 20],
-                    2 => [4, 11, 16, 23],
-                    _ => [6, 10, 15, 21],
-                };
-                
-                let temp = d;
-                d = c;
-                c = b;
-                let rot_amount = s[i % 4];
-                b = b.wrapping_add(rotl(a.wrapping_add(f).wrapping_add(words[word_idx]).wrapping_add(k), rot_amount));
-                a = temp;
+    /// Updates the hash state with bytes from an iterator, staging them in the
+    /// internal buffer and compressing each block as soon as it fills up
+    pub fn update_from_iter<I: IntoIterator<Item = u8>>(&mut self, iter: I) -> &mut Self {
+        for byte in iter {
+            self.buffer[self.buffer_len] = byte;
+            self.buffer_len += 1;
+            self.total_bytes = self.total_bytes.wrapping_add(1);
+            
+            if self.buffer_len == 64 {
+                let block_data = self.buffer;
+                self.process_block(&block_data);
+                self.buffer_len = 0;
             }
         }
-        
-        // Update state with the result
-        self.state[0] = self.state[0].wrapping_add(a);
-        self.state[1] = self.state[1].wrapping_add(b);
-        self.state[2] = self.state[2].wrapping_add(c);
-        self.state[3] = self.state[3].wrapping_add(d);
+        self
+    }
+    
+    /// Processes a single 64-byte block
+    fn process_block(&mut self, block: &[u8]) {
+        compress_block(&mut self.state, block);
     }
     
     /// Finalizes the hash computation and returns the hash
     pub fn finalize(mut self) -> [u8; 16] {
-        // Add padding similar to MD5/SHA
-        let bit_len = self.total_bytes * 8;
-
+        self.finalize_pending()
+    }
+    
+    /// Extendable output: fills `out` with any number of bytes derived from
+    /// the message, then resets the hasher like `finalize_reset`
+    ///
+    /// Each 16-byte output block is one extra compression of the padded
+    /// final state with a block holding an XOF tag and the block counter, so
+    /// blocks are independent of each other and of the `finalize` digest.
+    /// Shorter outputs are prefixes of longer ones.
+    pub fn finalize_xof(&mut self, out: &mut [u8]) {
+        self.finalize_pending();
+        let root = self.state;
+        
+        for (counter, chunk) in (0u64..).zip(out.chunks_mut(16)) {
+            let mut block = [0u8; 64];
+            block[..8].copy_from_slice(Self::XOF_TAG);
+            block[8..16].copy_from_slice(&counter.to_le_bytes());
+            
+            self.state = root;
+            self.process_block(&block);
+            chunk.copy_from_slice(&self.state_bytes()[..chunk.len()]);
+        }
+        
+        self.reset();
+    }
+    
+    /// Returns the hasher to its freshly created state. Keyed hashers keep
+    /// their key; hashers restored with `from_midstate` or `from_state` reset
+    /// to `SMCHash::new()`.
+    pub fn reset(&mut self) {
+        self.state = self.initial_state;
+        self.buffer_len = 0;
+        self.total_bytes = 0;
+    }
+    
+    /// Same digest as `finalize`, but resets the hasher instead of consuming
+    /// it so one instance can hash many messages
+    pub fn finalize_reset(&mut self) -> [u8; 16] {
+        let digest = self.finalize_pending();
+        self.reset();
+        digest
+    }
+    
+    // Pads the buffered bytes and returns the digest. Leaves the hasher in a
+    // finalized state that must be reset before reuse.
+    fn finalize_pending(&mut self) -> [u8; 16] {
+        let tail = self.buffer;
+        self.finalize_tail(&tail[..self.buffer_len])
+    }
+    
+    /// Pads and processes the final partial block (`tail`, less than 64 bytes)
+    /// on the stack and returns the digest
+    fn finalize_tail(&mut self, tail: &[u8]) -> [u8; 16] {
+        // Add padding similar to MD5/SHA. Like them, the length suffix is the
+        // message length in bits modulo 2^64, so messages of 2^61 bytes or
+        // more wrap instead of overflowing.
+        let bit_len = self.total_bytes.wrapping_mul(8);
+        
+        let mut block = [0u8; 64];
+        block[..tail.len()].copy_from_slice(tail);
+        
         // Add a single '1' bit
-        self.buffer.push(0x80);
+        block[tail.len()] = 0x80;
         
-        // Pad with zeros to get 56 bytes mod 64
-        while self.buffer.len() % 64 != 56 {
-            self.buffer.push(0);
+        // No room left for the length: it goes in an extra block
+        if tail.len() >= 56 {
+            self.process_block(&block);
+            block = [0u8; 64];
         }
         
         // Append length as 64-bit little-endian integer
-        self.buffer.extend_from_slice(&bit_len.to_le_bytes());
+        block[56..].copy_from_slice(&bit_len.to_le_bytes());
+        self.process_block(&block);
+        self.state_bytes()
+    }
+    
+    // Convert state to bytes (16 bytes total)
+    fn state_bytes(&self) -> [u8; 16] {
+        state_to_bytes(&self.state)
+    }
+    
+    // Digests of this hasher's message followed by each of `suffixes`, as
+    // `LANES` independent messages through the multi-lane kernel. The hasher
+    // itself is left untouched.
+    fn finalize_lanes(&self, suffixes: &[[u8; 8]; LANES]) -> [[u8; 16]; LANES] {
+        // The pending bytes, a suffix and the padding span one or two blocks
+        let tail_len = self.buffer_len + 8;
+        let block_count = if tail_len < 56 { 1 } else { 2 };
+        let bit_len = self.total_bytes.wrapping_add(8).wrapping_mul(8);
         
-        // Process last blocks
-        while self.buffer.len() >= 64 {
-            // Create a separate buffer to avoid borrowing issues
-            let block_data = self.buffer[0..64].to_vec();
-            self.process_block(&block_data);
-            self.buffer.drain(0..64);
-        }
-
-        // Convert state to bytes (16 bytes total)
-        let mut result = [0u8; 16];
-        for i in 0..4 {
-            let bytes = self.state[i].to_le_bytes();
-            result[i*4..(i+1)*4].copy_from_slice(&bytes);
+        let mut tails = [[0u8; 128]; LANES];
+        for (tail, suffix) in tails.iter_mut().zip(suffixes) {
+            tail[..self.buffer_len].copy_from_slice(&self.buffer[..self.buffer_len]);
+            tail[self.buffer_len..tail_len].copy_from_slice(suffix);
+            tail[tail_len] = 0x80;
+            tail[block_count * 64 - 8..block_count * 64].copy_from_slice(&bit_len.to_le_bytes());
         }
         
-        result
+        let mut states = [self.state; LANES];
+        for block in 0..block_count {
+            let blocks = tails.map(|tail| tail[block * 64..(block + 1) * 64].try_into().unwrap());
+            lanes::compress(&mut states, &blocks);
+        }
+        states.map(|state| state_to_bytes(&state))
     }
     
     /// Simple one-shot hash function for convenience
@@ -144,193 +435,4754 @@ impl SMCHash {
         hasher.finalize()
     }
     
+    /// One-shot hash of `data` in the domain named by `tag`, see
+    /// `new_with_personalization`
+    pub fn hash_personalized(tag: &[u8], data: &[u8]) -> [u8; 16] {
+        let mut hasher = SMCHash::new_with_personalization(tag);
+        hasher.update(data);
+        hasher.finalize()
+    }
+    
+    /// Hashes many independent messages at once, giving the same digests as
+    /// calling `hash` on each. Messages are interleaved `LANES` at a time
+    /// through the multi-lane (SIMD where available) compression kernel,
+    /// which makes this much faster for batches such as a block's
+    /// transactions.
+    #[cfg(feature = "alloc")]
+    pub fn hash_many(inputs: &[&[u8]]) -> Vec<[u8; 16]> {
+        // Batch messages of similar length so their lanes finish together
+        let mut order: Vec<usize> = (0..inputs.len()).collect();
+        order.sort_by_key(|&i| inputs[i].len());
+        
+        let mut digests = vec![[0u8; 16]; inputs.len()];
+        for batch in order.chunks(LANES) {
+            let block_counts: [usize; LANES] =
+                core::array::from_fn(|lane| batch.get(lane).map_or(0, |&i| padded_block_count(inputs[i].len())));
+            let mut states = [Self::IV; LANES];
+            
+            for block in 0..block_counts.iter().copied().max().unwrap_or(0) {
+                let blocks = core::array::from_fn(|lane| match batch.get(lane) {
+                    Some(&i) if block < block_counts[lane] => padded_block(inputs[i], block),
+                    _ => [0; 64],
+                });
+                
+                // Lanes that are already done (or unused) keep their state
+                let before = states;
+                lanes::compress(&mut states, &blocks);
+                for lane in 0..LANES {
+                    if block >= block_counts[lane] {
+                        states[lane] = before[lane];
+                    }
+                }
+            }
+            
+            for (lane, &i) in batch.iter().enumerate() {
+                digests[i] = state_to_bytes(&states[lane]);
+            }
+        }
+        digests
+    }
+    
+    /// Same as `hash`, accepting anything that can be viewed as bytes
+    /// (`&str`, `String`, `Vec<u8>`, byte arrays, ...)
+    pub fn hash_of(data: impl AsRef<[u8]>) -> [u8; 16] {
+        Self::hash(data.as_ref())
+    }
+    
+    /// One-shot hash of a fixed-size array that never allocates: full blocks
+    /// are processed in place and the padding is staged on the stack.
+    /// Produces the same digest as `hash`.
+    pub fn hash_fixed<const N: usize>(data: &[u8; N]) -> [u8; 16] {
+        let mut hasher = SMCHash::new();
+        hasher.total_bytes = N as u64;
+        
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            hasher.process_block(block);
+        }
+        hasher.finalize_tail(blocks.remainder())
+    }
+    
+    /// Hashes everything read from `reader`, reading in fixed-size chunks so
+    /// memory use stays bounded regardless of the input size
+    #[cfg(feature = "std")]
+    pub fn hash_reader<R: Read>(reader: R) -> io::Result<[u8; 16]> {
+        Self::hash_reader_with_progress(reader, |_| {})
+    }
+    
+    /// Hashes the file at `path` by memory-mapping it, which avoids copying
+    /// large files through a read buffer. Files that cannot be mapped (pipes,
+    /// some special files) fall back to buffered reads.
+    ///
+    /// The file must not be modified or truncated by another process while it
+    /// is being hashed; use `hash_reader` for files that may change underneath.
+    #[cfg(feature = "mmap")]
+    pub fn hash_file(path: &Path) -> io::Result<[u8; 16]> {
+        let file = File::open(path)?;
+        // SAFETY: the map is only read, and dropped before returning. The
+        // caller guarantees the file is not changed while it is mapped.
+        match unsafe { memmap2::Mmap::map(&file) } {
+            Ok(map) => Ok(Self::hash(&map)),
+            Err(_) => Self::hash_reader(file),
+        }
+    }
+    
+    /// Hashes everything read from `reader` in fixed-size chunks, calling
+    /// `progress` with the total number of bytes consumed after each chunk
+    #[cfg(feature = "std")]
+    pub fn hash_reader_with_progress<R: Read, F: FnMut(u64)>(
+        mut reader: R,
+        mut progress: F,
+    ) -> io::Result<[u8; 16]> {
+        let mut hasher = SMCHash::new();
+        let mut chunk = vec![0u8; READ_CHUNK_SIZE];
+        
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            hasher.update(&chunk[..read]);
+            progress(hasher.total_bytes);
+        }
+        
+        Ok(hasher.finalize())
+    }
+    
     /// Fast verification method for blockchain applications
     /// Returns true if the hash is valid for the given data
     pub fn verify(data: &[u8], expected_hash: &[u8; 16]) -> bool {
         let computed_hash = Self::hash(data);
         
         // Time-constant comparison to prevent timing attacks
-        let mut result = 0;
-        for i in 0..16 {
-            result |= computed_hash[i] ^ expected_hash[i];
-        }
-        
-        result == 0
+        ct_eq(&computed_hash, expected_hash)
     }
     
     /// Creates a proof of work by finding a nonce that produces a hash with
     /// the specified number of leading zero bits
+    ///
+    /// Nonces are tried in order starting from 0, so the returned nonce is
+    /// always the smallest valid one. Verifiers and test fixtures rely on
+    /// this being deterministic. The search runs through `PowVerifier::find`,
+    /// using the SIMD kernel where the CPU supports it.
+    ///
+    /// A difficulty of 0 accepts any hash, so nonce 0 is returned immediately.
+    ///
+    /// Panics if `difficulty` exceeds `MAX_DIFFICULTY`, as no hash could ever
+    /// satisfy it and the search would never end.
     pub fn create_proof_of_work(data: &[u8], difficulty: u8) -> (u64, [u8; 16]) {
-        let mut nonce: u64 = 0;
-        let target_mask = if difficulty >= 8 {
-            0xFF
-        } else {
-            0xFF >> (8 - difficulty)
-        };
+        Self::create_proof_of_work_with_progress(data, difficulty, |_| {})
+    }
+    
+    /// Same as `create_proof_of_work`, calling `progress` with the number of
+    /// attempts made so far every `PROGRESS_INTERVAL` attempts
+    pub fn create_proof_of_work_with_progress<F: FnMut(u64)>(
+        data: &[u8],
+        difficulty: u8,
+        mut progress: F,
+    ) -> (u64, [u8; 16]) {
+        assert_difficulty_in_range(difficulty);
+        let verifier = PowVerifier::new(data);
+        
+        for start in (0..u64::MAX).step_by(PROGRESS_INTERVAL as usize) {
+            let end = start.saturating_add(PROGRESS_INTERVAL);
+            if let Some(nonce) = verifier.find(start..end, difficulty) {
+                return (nonce, verifier.hash(nonce));
+            }
+            progress(end);
+        }
+        panic!("no nonce meets difficulty {}", difficulty);
+    }
+    
+    /// Same as `create_proof_of_work`, calling `callback` every `interval`
+    /// attempts with the attempts made so far and the average hashrate
+    /// (hashes per second) since the search started, or 0.0 if no time has
+    /// measurably passed. Returning `ControlFlow::Break` from the callback
+    /// aborts the search and gives `None`, e.g. when the user cancels from a
+    /// UI. `None` only ever means an abort: like `create_proof_of_work`, the
+    /// search panics if no nonce up to `u64::MAX` meets `difficulty`.
+    ///
+    /// Panics if `interval` is 0 or `difficulty` exceeds `MAX_DIFFICULTY`.
+    #[cfg(feature = "std")]
+    pub fn create_proof_of_work_with_callback<F: FnMut(u64, f64) -> ControlFlow<()>>(
+        data: &[u8],
+        difficulty: u8,
+        interval: u64,
+        mut callback: F,
+    ) -> Option<(u64, [u8; 16])> {
+        assert!(interval > 0, "progress interval must be non-zero");
+        assert_difficulty_in_range(difficulty);
+        let verifier = PowVerifier::new(data);
+        let start = Instant::now();
         
+        let mut batch_start = 0u64;
         loop {
-            let mut hasher = SMCHash::new();
-            hasher.update(data);
-            hasher.update(&nonce.to_le_bytes());
-            let hash = hasher.finalize();
-            
-            // Check if we have the required number of leading zeros
-            let zeros_required = difficulty / 8;
-            let bits_in_last_byte = difficulty % 8;
-            
-            let mut valid = true;
-            
-            // Check full zero bytes
-            for i in 0..zeros_required as usize {
-                if hash[i] != 0 {
-                    valid = false;
-                    break;
-                }
+            let end = batch_start.saturating_add(interval);
+            if let Some(nonce) = verifier.find(batch_start..end, difficulty) {
+                return Some((nonce, verifier.hash(nonce)));
             }
-            
-            // Check partial zero byte if needed
-            if valid && bits_in_last_byte > 0 {
-                valid = (hash[zeros_required as usize] & target_mask) == 0;
+            if end == u64::MAX {
+                // The range above is half-open, so the last nonce is left over
+                if verifier.check(u64::MAX, difficulty) {
+                    return Some((u64::MAX, verifier.hash(u64::MAX)));
+                }
+                panic!("no nonce meets difficulty {}", difficulty);
             }
             
-            if valid {
-                return (nonce, hash);
+            let elapsed = start.elapsed().as_secs_f64();
+            let hashes_per_sec = if elapsed > 0.0 { end as f64 / elapsed } else { 0.0 };
+            if callback(end, hashes_per_sec).is_break() {
+                return None;
             }
-            
-            nonce += 1;
+            batch_start = end;
         }
     }
     
-    /// Verifies a proof of work
-    pub fn verify_proof_of_work(data: &[u8], nonce: u64, difficulty: u8, expected_hash: &[u8; 16]) -> bool {
-        let mut hasher = SMCHash::new();
-        hasher.update(data);
-        hasher.update(&nonce.to_le_bytes());
-        let hash = hasher.finalize();
+    /// Async `create_proof_of_work` for tokio-based nodes: the search runs on
+    /// a `spawn_blocking` worker, so no executor thread is blocked
+    ///
+    /// Cancel-safe: dropping the future (e.g. losing a `select!` against an
+    /// incoming block) stops the worker within one `PROGRESS_INTERVAL` batch
+    /// instead of leaving it mining in the background. Must be awaited
+    /// within a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub async fn create_proof_of_work_async(data: Vec<u8>, difficulty: u8) -> (u64, [u8; 16]) {
+        assert_difficulty_in_range(difficulty);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let _guard = CancelOnDrop(Arc::clone(&cancel));
         
-        // Verify hash matches expected hash
-        if hash != *expected_hash {
-            return false;
+        let search = tokio::task::spawn_blocking(move || {
+            Self::create_proof_of_work_cancellable(&data, difficulty, &cancel)
+        });
+        match search.await {
+            Ok(result) => result.expect("no nonce meets the difficulty"),
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
         }
+    }
+    
+    /// Same as `create_proof_of_work` with a numeric target instead of a
+    /// leading-zero-bit difficulty: returns the smallest nonce whose hash
+    /// meets `target` (see `meets_target`)
+    ///
+    /// Every target can be met in principle, but very small ones take
+    /// correspondingly long: the expected number of attempts is about
+    /// `2^128 / (target + 1)`.
+    pub fn create_proof_of_work_target(data: &[u8], target: u128) -> (u64, [u8; 16]) {
+        let verifier = PowVerifier::new(data);
         
-        // Verify difficulty requirement
-        let zeros_required = difficulty / 8;
-        let bits_in_last_byte = difficulty % 8;
-        let target_mask = if bits_in_last_byte == 0 {
-            0
-        } else {
-            0xFF >> (8 - bits_in_last_byte)
-        };
-        
-        // Check full zero bytes
-        for i in 0..zeros_required as usize {
-            if hash[i] != 0 {
-                return false;
+        for start in (0..u64::MAX).step_by(PROGRESS_INTERVAL as usize) {
+            let end = start.saturating_add(PROGRESS_INTERVAL);
+            if let Some(nonce) = verifier.find_target(start..end, target) {
+                return (nonce, verifier.hash(nonce));
             }
         }
+        panic!("no nonce meets target {:#034x}", target);
+    }
+    
+    /// Same as `create_proof_of_work`, but accepts the first hash satisfying
+    /// `predicate` instead of a difficulty, e.g. to require trailing zero
+    /// bytes or a vanity prefix in research code. The search runs through
+    /// `PowVerifier::find_with`. The standard verifiers only know
+    /// difficulties and targets: check solutions by applying the predicate
+    /// to `PowVerifier::hash`.
+    ///
+    /// Runs until a solution is found, so a predicate that is (almost)
+    /// never satisfied never returns.
+    pub fn create_proof_of_work_with<P: Fn(&[u8; 16]) -> bool>(data: &[u8], predicate: P) -> (u64, [u8; 16]) {
+        let verifier = PowVerifier::new(data);
         
-        // Check partial zero byte if needed
-        if bits_in_last_byte > 0 {
-            if (hash[zeros_required as usize] & target_mask) != 0 {
-                return false;
+        for start in (0..u64::MAX).step_by(PROGRESS_INTERVAL as usize) {
+            let end = start.saturating_add(PROGRESS_INTERVAL);
+            if let Some(nonce) = verifier.find_with(start..end, &predicate) {
+                return (nonce, verifier.hash(nonce));
             }
         }
+        panic!("no nonce satisfies the predicate");
+    }
+    
+    /// Same as `create_proof_of_work` at a fractional difficulty: the
+    /// smallest nonce whose hash meets `difficulty.to_target()`
+    pub fn create_proof_of_work_fractional(data: &[u8], difficulty: FractionalDifficulty) -> (u64, [u8; 16]) {
+        Self::create_proof_of_work_target(data, difficulty.to_target())
+    }
+    
+    /// Verifies a proof of work at a fractional difficulty, see
+    /// `verify_proof_of_work_target`
+    pub fn verify_proof_of_work_fractional(
+        data: &[u8],
+        nonce: u64,
+        difficulty: FractionalDifficulty,
+        expected_hash: &[u8; 16],
+    ) -> bool {
+        Self::verify_proof_of_work_target(data, nonce, difficulty.to_target(), expected_hash)
+    }
+    
+    /// Verifies a proof of work against a numeric target: `expected_hash`
+    /// must be the hash of `data` and `nonce`, and meet `target`
+    pub fn verify_proof_of_work_target(data: &[u8], nonce: u64, target: u128, expected_hash: &[u8; 16]) -> bool {
+        let hash = Self::pow_hash(data, nonce);
+        ct_eq(&hash, expected_hash) && meets_target(&hash, target)
+    }
+    
+    /// Same as `create_proof_of_work`, but gives up and returns `None` once
+    /// `cancel` is set, e.g. by another thread when a competing block arrives
+    /// and the work is stale. The flag is checked every `PROGRESS_INTERVAL`
+    /// attempts, so cancellation takes effect within microseconds.
+    pub fn create_proof_of_work_cancellable(
+        data: &[u8],
+        difficulty: u8,
+        cancel: &AtomicBool,
+    ) -> Option<(u64, [u8; 16])> {
+        assert_difficulty_in_range(difficulty);
+        let verifier = PowVerifier::new(data);
         
-        true
+        for start in (0..u64::MAX).step_by(PROGRESS_INTERVAL as usize) {
+            if cancel.load(AtomicOrdering::Relaxed) {
+                return None;
+            }
+            let end = start.saturating_add(PROGRESS_INTERVAL);
+            if let Some(nonce) = verifier.find(start..end, difficulty) {
+                return Some((nonce, verifier.hash(nonce)));
+            }
+        }
+        None
     }
-}
-
-// Helper function: left rotation
-fn rotl(x: u32, n: u32) -> u32 {
-    (x << n) | (x >> (32 - n))
-}
-
-// Utility function to convert hash to hex string
-pub fn hash_to_hex(hash: &[u8; 16]) -> String {
-    hash.iter().map(|b| format!("{:02x}", b)).collect()
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
     
-    #[test]
-    fn test_empty_string() {
-        let hash = SMCHash::hash("".as_bytes());
-        // This is just a placeholder expected value - actual implementation would have its own value
-        assert_eq!(hash_to_hex(&hash).len(), 32); // 16 bytes = 32 hex chars
+    /// Same as `create_proof_of_work`, but tries at most `max_iters` nonces
+    /// (0 through `max_iters - 1`) and returns `None` if none of them meets
+    /// `difficulty`. Lets callers time-slice mining and keeps tests at high
+    /// difficulty from hanging.
+    pub fn create_proof_of_work_bounded(data: &[u8], difficulty: u8, max_iters: u64) -> Option<(u64, [u8; 16])> {
+        Self::search_nonce_range(data, difficulty, 0, max_iters)
     }
     
-    #[test]
-    fn test_verification() {
-        let data = "test data for verification".as_bytes();
-        let hash = SMCHash::hash(data);
-        assert!(SMCHash::verify(data, &hash));
+    /// Searches only nonces in `start..end`, returning the lowest valid one.
+    /// A pool coordinator can hand disjoint ranges to workers and take the
+    /// lowest result, which matches `create_proof_of_work` when the ranges
+    /// cover everything from 0 up to it.
+    pub fn search_nonce_range(data: &[u8], difficulty: u8, start: u64, end: u64) -> Option<(u64, [u8; 16])> {
+        assert_difficulty_in_range(difficulty);
+        let verifier = PowVerifier::new(data);
+        verifier.find(start..end, difficulty).map(|nonce| (nonce, verifier.hash(nonce)))
+    }
+    
+    /// Same as `create_proof_of_work`, but tries nonces upwards from
+    /// `start_nonce`, wrapping around to 0 after `u64::MAX`. Independent
+    /// miners working on the same data should each start at a random nonce
+    /// (or use distinct extranonces in the coinbase transaction) so they don't
+    /// repeat each other's work; the result is the first valid nonce at or
+    /// after `start_nonce` rather than the smallest one.
+    pub fn create_proof_of_work_from(data: &[u8], difficulty: u8, start_nonce: u64) -> (u64, [u8; 16]) {
+        assert_difficulty_in_range(difficulty);
+        let verifier = PowVerifier::new(data);
+        match find_wrapping(&verifier, start_nonce, |hash| meets_difficulty(hash, difficulty)) {
+            Some(nonce) => (nonce, verifier.hash(nonce)),
+            None => panic!("no nonce meets difficulty {}", difficulty),
+        }
+    }
+    
+    /// Same as `create_proof_of_work`, which now also hashes `data` only once
+    /// and resumes from that midstate for every nonce. Kept for existing
+    /// callers.
+    #[deprecated(note = "use create_proof_of_work")]
+    pub fn create_proof_of_work_fast(data: &[u8], difficulty: u8) -> (u64, [u8; 16]) {
+        Self::create_proof_of_work(data, difficulty)
+    }
+    
+    /// Finds the first `k` nonces that satisfy `difficulty`, in ascending
+    /// order, continuing the search past the first solution
+    #[cfg(feature = "alloc")]
+    pub fn mine_k_solutions(data: &[u8], difficulty: u8, k: usize) -> Vec<(u64, [u8; 16])> {
+        assert_difficulty_in_range(difficulty);
+        let midstate = Midstate::new(data);
+        (0..=u64::MAX)
+            .map(|nonce| (nonce, midstate.pow_hash(nonce)))
+            .filter(|(_, hash)| meets_difficulty(hash, difficulty))
+            .take(k)
+            .collect()
+    }
+    
+    /// Multi-threaded search that returns exactly what `create_proof_of_work`
+    /// would: the lowest valid nonce, regardless of thread scheduling
+    ///
+    /// The nonce space is dealt out to `threads` workers in fixed-size batches.
+    /// Once any worker finds a solution, workers finish the batches below it
+    /// and skip everything above, so the overall minimum is always found.
+    #[cfg(feature = "std")]
+    pub fn create_proof_of_work_parallel_lowest(
+        data: &[u8],
+        difficulty: u8,
+        threads: usize,
+    ) -> (u64, [u8; 16]) {
+        const BATCH_SIZE: u64 = 1024;
         
-        // Modify data and ensure verification fails
-        let modified_data = "test data for verificationX".as_bytes();
-        assert!(!SMCHash::verify(modified_data, &hash));
+        assert_difficulty_in_range(difficulty);
+        let threads = threads.max(1) as u64;
+        let verifier = PowVerifier::new(data);
+        let best = AtomicU64::new(u64::MAX);
+        
+        thread::scope(|scope| {
+            for worker in 0..threads {
+                let verifier = &verifier;
+                let best = &best;
+                scope.spawn(move || {
+                    let mut batch_start = worker * BATCH_SIZE;
+                    while batch_start < best.load(AtomicOrdering::Acquire) {
+                        let batch_end = batch_start.saturating_add(BATCH_SIZE);
+                        if let Some(nonce) = verifier.find(batch_start..batch_end, difficulty) {
+                            best.fetch_min(nonce, AtomicOrdering::AcqRel);
+                            return;
+                        }
+                        
+                        batch_start = match batch_start.checked_add(threads * BATCH_SIZE) {
+                            Some(next) => next,
+                            None => return,
+                        };
+                    }
+                });
+            }
+        });
+        
+        let nonce = best.into_inner();
+        (nonce, verifier.hash(nonce))
     }
     
-    #[test]
-    fn test_proof_of_work() {
-        let data = "blockchain data".as_bytes();
-        let difficulty = 8; // 8 bits = 1 byte of leading zeros
+    /// Multi-threaded search for a single block: returns the first valid
+    /// nonce any of `threads` workers finds, then stops the others
+    ///
+    /// The nonce space is dealt out in interleaved batches, so workers never
+    /// repeat each other's work. Unlike `create_proof_of_work_parallel_lowest`
+    /// the result depends on thread scheduling: any valid nonce may win, not
+    /// necessarily the lowest. Use this when only speed matters.
+    #[cfg(feature = "std")]
+    pub fn create_proof_of_work_parallel(data: &[u8], difficulty: u8, threads: usize) -> (u64, [u8; 16]) {
+        const BATCH_SIZE: u64 = 1024;
         
-        let (nonce, hash) = SMCHash::create_proof_of_work(data, difficulty);
-        assert!(SMCHash::verify_proof_of_work(data, nonce, difficulty, &hash));
+        assert_difficulty_in_range(difficulty);
+        let threads = threads.max(1) as u64;
+        let verifier = PowVerifier::new(data);
+        let found = AtomicBool::new(false);
+        let solution = OnceLock::new();
         
-        // Test first byte is zero (8 bits of difficulty)
-        assert_eq!(hash[0], 0);
+        thread::scope(|scope| {
+            for worker in 0..threads {
+                let (verifier, found, solution) = (&verifier, &found, &solution);
+                scope.spawn(move || {
+                    let mut batch_start = worker * BATCH_SIZE;
+                    while !found.load(AtomicOrdering::Acquire) {
+                        let batch_end = batch_start.saturating_add(BATCH_SIZE);
+                        if let Some(nonce) = verifier.find(batch_start..batch_end, difficulty) {
+                            // Only the first worker to get here sets the solution
+                            let _ = solution.set(nonce);
+                            found.store(true, AtomicOrdering::Release);
+                            return;
+                        }
+                        
+                        batch_start = match batch_start.checked_add(threads * BATCH_SIZE) {
+                            Some(next) => next,
+                            None => return,
+                        };
+                    }
+                });
+            }
+        });
+        
+        let nonce = solution.into_inner().expect("no nonce meets the difficulty");
+        (nonce, verifier.hash(nonce))
     }
     
-    #[test]
-    fn test_different_inputs_produce_different_hashes() {
-        let hash1 = SMCHash::hash("input1".as_bytes());
-        let hash2 = SMCHash::hash("input2".as_bytes());
-        assert_ne!(hash1, hash2);
+    /// Searches nonces for `time_budget` and returns the one whose hash is
+    /// numerically smallest (big-endian), for "closest wins" style mining.
+    /// At least nonce 0 is always tried.
+    #[cfg(feature = "std")]
+    pub fn mine_lowest(data: &[u8], time_budget: Duration) -> (u64, [u8; 16]) {
+        // Only check the clock every few attempts to keep the loop cheap
+        const CLOCK_CHECK_INTERVAL: u64 = 1024;
+        
+        let start = Instant::now();
+        let midstate = Midstate::new(data);
+        let mut best = (0, midstate.pow_hash(0));
+        let mut best_value = hash_to_u128(&best.1);
+        
+        let mut nonce: u64 = 1;
+        while !nonce.is_multiple_of(CLOCK_CHECK_INTERVAL) || start.elapsed() < time_budget {
+            let hash = midstate.pow_hash(nonce);
+            let value = hash_to_u128(&hash);
+            if value < best_value {
+                best = (nonce, hash);
+                best_value = value;
+            }
+            
+            nonce = match nonce.checked_add(1) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        
+        best
     }
-}
-
-// Example usage in a blockchain context
-#[derive(Debug)]
-pub struct Block {
-    pub prev_hash: [u8; 16],
-    pub data: Vec<u8>,
-    pub timestamp: u64,
-    pub nonce: u64,
-    pub hash: [u8; 16],
-}
-
-impl Block {
-    pub fn new(prev_hash: [u8; 16], data: Vec<u8>, timestamp: u64, difficulty: u8) -> Self {
-        let mut block = Block {
-            prev_hash,
-            data,
-            timestamp,
-            nonce: 0,
-            hash: [0; 16],
-        };
+    
+    /// Verifies a proof of work. Difficulties above `MAX_DIFFICULTY` can never
+    /// be met and are rejected.
+    pub fn verify_proof_of_work(data: &[u8], nonce: u64, difficulty: u8, expected_hash: &[u8; 16]) -> bool {
+        let hash = Self::pow_hash(data, nonce);
         
-        // Create the block hash with proof of work
-        let block_data = block.get_hashable_data();
-        let (nonce, hash) = SMCHash::create_proof_of_work(&block_data, difficulty);
+        // Verify hash matches expected hash
+        if !ct_eq(&hash, expected_hash) {
+            return false;
+        }
         
-        block.nonce = nonce;
-        block.hash = hash;
-        block
+        meets_difficulty(&hash, difficulty)
     }
     
-    fn get_hashable_data(&self) -> Vec<u8> {
-        let mut data = Vec::new();
-        data.extend_from_slice(&self.prev_hash);
-        data.extend_from_slice(&self.data);
-        data.extend_from_slice(&self.timestamp.to_le_bytes());
-        data
+    /// Verifies many `(data, nonce, difficulty, expected_hash)` proofs of
+    /// work, giving the same results as `verify_proof_of_work` on each item
+    /// in order. Large batches are split across the available cores, and
+    /// each thread hashes its items `LANES` at a time through the multi-lane
+    /// kernel, as `hash_many` does. Meant for re-validating a whole chain.
+    #[cfg(feature = "std")]
+    pub fn verify_proof_of_work_batch(items: &[(&[u8], u64, u8, [u8; 16])]) -> Vec<bool> {
+        // Below this many items per thread, spawning costs more than it saves
+        const MIN_CHUNK: usize = 256;
+        
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = items.len().div_ceil(threads).max(MIN_CHUNK);
+        let mut results = vec![false; items.len()];
+        
+        if items.len() <= chunk_size {
+            Self::verify_pow_chunk(items, &mut results);
+        } else {
+            thread::scope(|scope| {
+                for (items, results) in items.chunks(chunk_size).zip(results.chunks_mut(chunk_size)) {
+                    scope.spawn(move || Self::verify_pow_chunk(items, results));
+                }
+            });
+        }
+        results
     }
     
-    pub fn validate(&self, difficulty: u8) -> bool {
-        let block_data = self.get_hashable_data();
-        SMCHash::verify_proof_of_work(&block_data, self.nonce, difficulty, &self.hash)
+    #[cfg(feature = "std")]
+    fn verify_pow_chunk(items: &[(&[u8], u64, u8, [u8; 16])], results: &mut [bool]) {
+        let messages: Vec<Vec<u8>> =
+            items.iter().map(|(data, nonce, _, _)| [*data, &nonce.to_le_bytes()[..]].concat()).collect();
+        let messages: Vec<&[u8]> = messages.iter().map(Vec::as_slice).collect();
+        
+        let hashes = Self::hash_many(&messages);
+        for ((item, hash), result) in items.iter().zip(&hashes).zip(results) {
+            *result = ct_eq(hash, &item.3) && meets_difficulty(hash, item.2);
+        }
     }
-}
\ No newline at end of file
+    
+    /// Checks a proof of work by recomputing the hash from `data` and `nonce`,
+    /// without trusting a hash supplied by the sender
+    pub fn check_proof_of_work(data: &[u8], nonce: u64, difficulty: u8) -> bool {
+        meets_difficulty(&Self::pow_hash(data, nonce), difficulty)
+    }
+    
+    /// Hashes `data` followed by the little-endian nonce
+    fn pow_hash(data: &[u8], nonce: u64) -> [u8; 16] {
+        let mut hasher = SMCHash::new();
+        hasher.update(data);
+        hasher.update(&nonce.to_le_bytes());
+        hasher.finalize()
+    }
+}
+
+// First nonce from `start_nonce` upwards, wrapping around to 0 after
+// `u64::MAX`, whose hash `accept`s
+fn find_wrapping<P: Fn(&[u8; 16]) -> bool>(verifier: &PowVerifier, start_nonce: u64, accept: P) -> Option<u64> {
+    for (from, to) in [(start_nonce, u64::MAX), (0, start_nonce)] {
+        for start in (from..to).step_by(PROGRESS_INTERVAL as usize) {
+            let end = start.saturating_add(PROGRESS_INTERVAL).min(to);
+            if let Some(nonce) = verifier.find_with(start..end, &accept) {
+                return Some(nonce);
+            }
+        }
+        // Ranges exclude their end, so `u64::MAX` is tried on its own
+        if to == u64::MAX && accept(&verifier.hash(to)) {
+            return Some(to);
+        }
+    }
+    None
+}
+
+// Sets the flag when dropped, cancelling the search that watches it
+#[cfg(feature = "tokio")]
+struct CancelOnDrop(Arc<AtomicBool>);
+
+#[cfg(feature = "tokio")]
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, AtomicOrdering::Relaxed);
+    }
+}
+
+// Miners would search forever for a difficulty no 128-bit hash can meet
+fn assert_difficulty_in_range(difficulty: u8) {
+    assert!(
+        difficulty <= MAX_DIFFICULTY,
+        "difficulty {} exceeds the maximum of {} bits",
+        difficulty,
+        MAX_DIFFICULTY
+    );
+}
+
+/// Whether `hash`, read as a big-endian number, is at most `target`. Lower
+/// targets are harder; unlike leading-zero difficulty, any target value can
+/// be used, so difficulty can be adjusted smoothly rather than in powers of
+/// two. Difficulty `d` corresponds to the target `u128::MAX >> d`.
+pub fn meets_target(hash: &[u8; 16], target: u128) -> bool {
+    hash_to_u128(hash) <= target
+}
+
+// Checks that the hash starts with `difficulty` zero bits, most significant
+// bit of the first byte first. Always false above `MAX_DIFFICULTY`.
+fn meets_difficulty(hash: &[u8; 16], difficulty: u8) -> bool {
+    leading_zero_bits(hash) >= difficulty as u32
+}
+
+impl Default for SMCHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streams data into the hasher, e.g. with `io::copy(&mut reader, &mut hasher)`.
+/// Writes never fail and always consume the whole buffer.
+#[cfg(feature = "std")]
+impl io::Write for SMCHash {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `core::hash::Hasher` over SMCHash, built by `SmcBuildHasher`. `finish`
+/// returns the first 8 digest bytes as a little-endian u64.
+#[derive(Clone, Default)]
+pub struct SmcHasher {
+    inner: SMCHash,
+}
+
+impl Hasher for SmcHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.inner.update(bytes);
+    }
+    
+    fn finish(&self) -> u64 {
+        let digest = self.inner.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+/// `BuildHasher` for `HashMap<K, V, SmcBuildHasher>`, so in-memory indexes
+/// hash with SMCHash like the rest of the node. Every hasher it builds is
+/// seeded with `SMCHash::with_seed`.
+#[derive(Clone)]
+pub struct SmcBuildHasher {
+    seeded: SMCHash,
+}
+
+impl SmcBuildHasher {
+    /// Uses a random seed, making tables resistant to hash flooding
+    #[cfg(feature = "std")]
+    pub fn new() -> Self {
+        // RandomState is randomly keyed per process; hashing nothing with it
+        // gives a random u64 without another dependency
+        Self::with_seed(std::collections::hash_map::RandomState::new().build_hasher().finish())
+    }
+    
+    /// Uses a fixed seed, for tables whose iteration order must be
+    /// reproducible across runs
+    pub fn with_seed(seed: u64) -> Self {
+        SmcBuildHasher {
+            seeded: SMCHash::with_seed(seed),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for SmcBuildHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildHasher for SmcBuildHasher {
+    type Hasher = SmcHasher;
+    
+    fn build_hasher(&self) -> SmcHasher {
+        SmcHasher {
+            inner: self.seeded.clone(),
+        }
+    }
+}
+
+// RustCrypto trait impls, so SMCHash can be used wherever a `D: Digest` bound
+// is required. `Digest` itself comes from digest's blanket impl.
+#[cfg(feature = "digest")]
+impl digest::HashMarker for SMCHash {}
+
+#[cfg(feature = "digest")]
+impl digest::OutputSizeUser for SMCHash {
+    type OutputSize = digest::consts::U16;
+}
+
+#[cfg(feature = "digest")]
+impl digest::Update for SMCHash {
+    fn update(&mut self, data: &[u8]) {
+        SMCHash::update(self, data);
+    }
+}
+
+#[cfg(feature = "digest")]
+impl digest::FixedOutput for SMCHash {
+    fn finalize_into(self, out: &mut digest::Output<Self>) {
+        out.copy_from_slice(&SMCHash::finalize(self));
+    }
+}
+
+#[cfg(feature = "digest")]
+impl digest::Reset for SMCHash {
+    fn reset(&mut self) {
+        SMCHash::reset(self);
+    }
+}
+
+// Helper function: left rotation, defined for any `n` (taken modulo 32)
+fn rotl(x: u32, n: u32) -> u32 {
+    x.rotate_left(n)
+}
+
+// The compression function: mixes one 64-byte block into `state`. The
+// multi-lane kernels in `lanes` compute exactly the same function.
+fn compress_block(state: &mut [u32; 4], block: &[u8]) {
+    // Convert block to sixteen 32-bit words
+    let mut words = [0u32; 16];
+    for (i, word) in words.iter_mut().enumerate() {
+        let start = i * 4;
+        let word_bytes: [u8; 4] = block[start..start + 4].try_into().unwrap();
+        *word = u32::from_le_bytes(word_bytes);
+    }
+    
+    // Save current state
+    let mut a = state[0];
+    let mut b = state[1];
+    let mut c = state[2];
+    let mut d = state[3];
+    
+    // Main mixing function - 4 rounds of operations
+    for round in 0..4 {
+        for i in 0..16 {
+            let f = match round {
+                0 => (b & c) | (!b & d),             // Round 1: if b then c else d
+                1 => (b & d) | (c & !d),             // Round 2: different bit mixing
+                2 => b ^ c ^ d,                     // Round 3: XOR
+                _ => c ^ (b | !d),                  // Round 4: alternative mixing
+            };
+            
+            let temp = d;
+            d = c;
+            c = b;
+            let sum = a.wrapping_add(f).wrapping_add(words[step_word(round, i)]).wrapping_add(step_constant(round, i));
+            b = b.wrapping_add(rotl(sum, step_shift(round, i)));
+            a = temp;
+        }
+    }
+    
+    // Update state with the result
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+}
+
+// Number of blocks in a message of `len` bytes once padded: the message, a
+// 0x80 byte and the 8-byte length, rounded up to whole blocks
+#[cfg(feature = "alloc")]
+fn padded_block_count(len: usize) -> usize {
+    (len + 9).div_ceil(64)
+}
+
+// Block `index` of `message` after the same padding as `finalize`
+#[cfg(feature = "alloc")]
+fn padded_block(message: &[u8], index: usize) -> [u8; 64] {
+    let start = index * 64;
+    let mut block = [0u8; 64];
+    
+    let data = message.get(start..).unwrap_or(&[]);
+    let copied = data.len().min(64);
+    block[..copied].copy_from_slice(&data[..copied]);
+    
+    if (start..start + 64).contains(&message.len()) {
+        block[message.len() - start] = 0x80;
+    }
+    if index + 1 == padded_block_count(message.len()) {
+        let bit_len = (message.len() as u64).wrapping_mul(8);
+        block[56..].copy_from_slice(&bit_len.to_le_bytes());
+    }
+    block
+}
+
+// Serializes state words little-endian, giving the digest
+fn state_to_bytes(state: &[u32; 4]) -> [u8; 16] {
+    let mut result = [0u8; 16];
+    for i in 0..4 {
+        let bytes = state[i].to_le_bytes();
+        result[i*4..(i+1)*4].copy_from_slice(&bytes);
+    }
+    
+    result
+}
+
+// Index of the message word mixed in at step `i` of `round`
+const fn step_word(round: usize, i: usize) -> usize {
+    match round {
+        0 => i,                            // Sequential in first round
+        1 => (5*i + 1) % 16,               // Different permutation per round
+        2 => (3*i + 5) % 16,
+        _ => (7*i) % 16,
+    }
+}
+
+// Additive constant for step `i` of `round`
+const fn step_constant(round: usize, i: usize) -> u32 {
+    let k = match round {
+        0 => 0x79cc4519,
+        1 => 0x9d8a7a87,
+        2 => 0xe9b5dba5,
+        _ => 0xc19bf274,
+    };
+    k + i as u32
+}
+
+// Rotation amount for step `i` of `round`
+const fn step_shift(round: usize, i: usize) -> u32 {
+    let s = match round {
+        0 => [7, 12, 17, 22],
+        1 => [5, 9, 14, 20],
+        2 => [4, 11, 16, 23],
+        _ => [6, 10, 15, 21],
+    };
+    s[i % 4]
+}
+
+// Utility function to convert hash to hex string
+#[cfg(feature = "alloc")]
+pub fn hash_to_hex(hash: &[u8; 16]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parses 32 hex digits (either case, optionally prefixed with `0x`) back
+/// into a hash. Inverse of `hash_to_hex`.
+pub fn hex_to_hash(hex: &str) -> Result<[u8; 16], HexError> {
+    let digits = hex.strip_prefix("0x").or_else(|| hex.strip_prefix("0X")).unwrap_or(hex);
+    
+    // Report bad characters before lengths: they are the more specific error
+    let offset = hex.len() - digits.len();
+    if let Some((index, ch)) = digits.char_indices().find(|(_, ch)| !ch.is_ascii_hexdigit()) {
+        return Err(HexError::InvalidChar { ch, index: offset + index });
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err(HexError::OddLength(digits.len()));
+    }
+    if digits.len() != 32 {
+        return Err(HexError::InvalidLength(digits.len()));
+    }
+    
+    let mut bytes = [0u8; 16];
+    for (byte, pair) in bytes.iter_mut().zip(digits.as_bytes().chunks_exact(2)) {
+        let high = (pair[0] as char).to_digit(16).unwrap();
+        let low = (pair[1] as char).to_digit(16).unwrap();
+        *byte = (high << 4 | low) as u8;
+    }
+    Ok(bytes)
+}
+
+/// Compares two byte slices in time that depends only on their length, not
+/// on where they differ. Slices of different lengths compare unequal
+/// immediately, as the length is not considered secret.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    
+    let mut result = 0;
+    for (x, y) in a.iter().zip(b) {
+        result |= x ^ y;
+    }
+    
+    // Keeps the optimizer from turning the loop into an early-exit compare
+    core::hint::black_box(result) == 0
+}
+
+/// Constant-time equality of two hashes or MAC tags. Every comparison
+/// against a hash that came from elsewhere (a peer, a block, a user) should
+/// go through this or `Hash16::ct_eq` rather than `==`.
+pub fn ct_eq(a: &[u8; 16], b: &[u8; 16]) -> bool {
+    // A single 128-bit XOR, with no data-dependent branch
+    let diff = u128::from_ne_bytes(*a) ^ u128::from_ne_bytes(*b);
+    core::hint::black_box(diff) == 0
+}
+
+/// Interprets a hash as a big-endian 128-bit integer. This is the canonical
+/// byte order for all difficulty and target comparisons.
+pub fn hash_to_u128(hash: &[u8; 16]) -> u128 {
+    u128::from_be_bytes(*hash)
+}
+
+/// Inverse of `hash_to_u128`
+pub fn u128_to_hash(value: u128) -> [u8; 16] {
+    value.to_be_bytes()
+}
+
+/// Orders hashes as big-endian numbers, smallest (most work) first. Usable
+/// directly with `slice::sort_by` to rank blocks by how lucky their hash is.
+pub fn cmp_hashes(a: &[u8; 16], b: &[u8; 16]) -> Ordering {
+    hash_to_u128(a).cmp(&hash_to_u128(b))
+}
+
+/// Number of leading zero bits of a hash, i.e. the highest difficulty it meets
+pub fn leading_zero_bits(hash: &[u8; 16]) -> u32 {
+    hash_to_u128(hash).leading_zeros()
+}
+
+/// A 16-byte SMCHash digest used as an identifier (block hashes, txids)
+///
+/// Keeps hashes from being confused with other 16-byte values such as
+/// addresses. Displays and parses as lowercase hex, and orders like
+/// `cmp_hashes` (big-endian, smallest first). Derefs to the raw bytes.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Hash16(pub [u8; 16]);
+
+impl Hash16 {
+    /// The all-zero hash, used as the `prev_hash` of a genesis block
+    pub const ZERO: Hash16 = Hash16([0; 16]);
+    
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+    
+    /// Constant-time equality, see `ct_eq`
+    pub fn ct_eq(&self, other: &Hash16) -> bool {
+        ct_eq(&self.0, &other.0)
+    }
+}
+
+impl From<[u8; 16]> for Hash16 {
+    fn from(bytes: [u8; 16]) -> Self {
+        Hash16(bytes)
+    }
+}
+
+impl From<Hash16> for [u8; 16] {
+    fn from(hash: Hash16) -> Self {
+        hash.0
+    }
+}
+
+impl AsRef<[u8]> for Hash16 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl core::ops::Deref for Hash16 {
+    type Target = [u8; 16];
+    
+    fn deref(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+
+impl fmt::LowerHex for Hash16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Hash16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+impl fmt::Debug for Hash16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hash16({:x})", self)
+    }
+}
+
+/// Parses as `hex_to_hash`
+impl core::str::FromStr for Hash16 {
+    type Err = SmcError;
+    
+    fn from_str(s: &str) -> Result<Self, SmcError> {
+        Ok(Hash16(hex_to_hash(s)?))
+    }
+}
+
+// Hex strings in human-readable formats (JSON, TOML), raw bytes otherwise
+#[cfg(feature = "serde")]
+impl serde::Serialize for Hash16 {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Hash16 {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let hex = String::deserialize(deserializer)?;
+            hex.parse().map_err(serde::de::Error::custom)
+        } else {
+            <[u8; 16]>::deserialize(deserializer).map(Hash16)
+        }
+    }
+}
+
+// `serde(with)` adapter giving raw 16-byte fields (addresses) the same
+// representation as `Hash16`
+#[cfg(feature = "serde")]
+mod hex_bytes {
+    use super::Hash16;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    
+    pub(crate) fn serialize<S: Serializer>(bytes: &[u8; 16], serializer: S) -> Result<S::Ok, S::Error> {
+        Hash16(*bytes).serialize(serializer)
+    }
+    
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 16], D::Error> {
+        Hash16::deserialize(deserializer).map(|hash| hash.0)
+    }
+}
+
+/// Expected number of nonces to try before solving the given difficulty,
+/// assuming uniformly distributed hashes (2^difficulty)
+pub fn expected_attempts(difficulty: u8) -> f64 {
+    // Built from the exponent bits, as `powi` needs std
+    f64::from_bits((1023 + difficulty as u64) << 52)
+}
+
+/// Difficulty in 1/256ths of a leading zero bit, so retargeting can move
+/// in steps of about 0.27% of the expected work instead of doubling it.
+/// Mining and verification go through the numeric target from `to_target`;
+/// whole-bit values give exactly the targets of the `u8` difficulties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FractionalDifficulty(u16);
+
+impl FractionalDifficulty {
+    /// Steps per bit of difficulty
+    pub const STEPS_PER_BIT: u16 = 256;
+    
+    /// The highest difficulty, equal to `MAX_DIFFICULTY` bits
+    pub const MAX: FractionalDifficulty = FractionalDifficulty(MAX_DIFFICULTY as u16 * Self::STEPS_PER_BIT);
+    
+    /// Difficulty of `steps` / 256 bits
+    ///
+    /// Panics above `FractionalDifficulty::MAX`.
+    pub fn from_steps(steps: u16) -> Self {
+        assert!(steps <= Self::MAX.0, "fractional difficulty {} exceeds {} steps", steps, Self::MAX.0);
+        FractionalDifficulty(steps)
+    }
+    
+    /// The same requirement as the whole-bit `difficulty`
+    ///
+    /// Panics if `difficulty` exceeds `MAX_DIFFICULTY`.
+    pub fn from_bits(difficulty: u8) -> Self {
+        assert_difficulty_in_range(difficulty);
+        FractionalDifficulty(difficulty as u16 * Self::STEPS_PER_BIT)
+    }
+    
+    pub fn steps(&self) -> u16 {
+        self.0
+    }
+    
+    /// Difficulty in (possibly fractional) bits
+    pub fn bits(&self) -> f64 {
+        self.0 as f64 / Self::STEPS_PER_BIT as f64
+    }
+    
+    /// Numeric target (see `meets_target`): `2^(128 - bits) - 1`, with the
+    /// fractional power of two from a deterministic fixed-point
+    /// approximation (within 0.013%) so every node derives the same target
+    pub fn to_target(&self) -> u128 {
+        let whole = (self.0 / Self::STEPS_PER_BIT) as u32;
+        let frac = (self.0 % Self::STEPS_PER_BIT) as u128;
+        if frac == 0 {
+            return u128::MAX.checked_shr(whole).unwrap_or(0);
+        }
+        
+        // 2^(128 - whole - frac/256) = 2^(127 - whole) * 2^((256 - frac)/256)
+        let factor = exp2_q16((Self::STEPS_PER_BIT as u128 - frac) << 8);
+        let shift = 127 - whole;
+        let value = if shift >= 16 { factor << (shift - 16) } else { factor >> (16 - shift) };
+        value - 1
+    }
+}
+
+// 2^(x / 65536) in 16.16 fixed point, for 0 <= x < 65536, by the cubic
+// approximation of aserti3-2d (within 0.013%, and increasing in `x`)
+fn exp2_q16(x: u128) -> u128 {
+    65536 + ((195_766_423_245_049 * x + 971_821_376 * x * x + 5127 * x * x * x + (1 << 47)) >> 48)
+}
+
+/// Proof-of-work requirement in either form the crate supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowTarget {
+    /// Leading zero bits, as in `create_proof_of_work`
+    Difficulty(u8),
+    /// Numeric target, as in `create_proof_of_work_target`
+    Target(u128),
+}
+
+impl PowTarget {
+    /// Chance that a single attempt meets the requirement
+    pub fn success_probability(&self) -> f64 {
+        match *self {
+            PowTarget::Difficulty(difficulty) if difficulty > MAX_DIFFICULTY => 0.0,
+            PowTarget::Difficulty(difficulty) => 1.0 / expected_attempts(difficulty),
+            // (target + 1) / 2^128, computed without overflowing at u128::MAX
+            PowTarget::Target(target) => (target as f64 + 1.0) / expected_attempts(128),
+        }
+    }
+}
+
+/// Mean time to find a solution to `work` at `hashrate` hashes per second.
+/// `Duration::MAX` if that is out of range (or the work can never be met).
+///
+/// Panics if `hashrate` is not positive.
+#[cfg(feature = "std")]
+pub fn estimate_time_to_solution(work: PowTarget, hashrate: f64) -> Duration {
+    assert!(hashrate > 0.0, "hashrate must be positive");
+    seconds_to_duration(1.0 / work.success_probability() / hashrate)
+}
+
+/// Time within which a solution to `work` is found with probability
+/// `percentile` (e.g. 0.5 for the median, 0.95 for a pessimistic bound) at
+/// `hashrate` hashes per second. Attempts until a solution are geometrically
+/// distributed, so the bounds are wide: the 95th percentile is about three
+/// times the mean and the median about 0.69 times.
+///
+/// Panics if `hashrate` is not positive or `percentile` is outside 0..=1.
+#[cfg(feature = "std")]
+pub fn solution_time_percentile(work: PowTarget, hashrate: f64, percentile: f64) -> Duration {
+    assert!(hashrate > 0.0, "hashrate must be positive");
+    assert!((0.0..=1.0).contains(&percentile), "percentile must be within 0..=1");
+    
+    // Smallest n with 1 - (1 - p)^n >= percentile, as a continuous value
+    let attempts = (-percentile).ln_1p() / (-work.success_probability()).ln_1p();
+    seconds_to_duration(attempts / hashrate)
+}
+
+// Saturates instead of panicking on huge or infinite estimates
+#[cfg(feature = "std")]
+fn seconds_to_duration(seconds: f64) -> Duration {
+    if seconds.is_nan() {
+        return Duration::ZERO;
+    }
+    Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX)
+}
+
+/// Highest difficulty one would expect to solve within `attempts` nonces,
+/// i.e. floor(log2(attempts)) clamped to 0..=128. Inverse of `expected_attempts`.
+pub fn difficulty_for_attempts(attempts: u64) -> u8 {
+    if attempts == 0 {
+        return 0;
+    }
+    attempts.ilog2().min(128) as u8
+}
+
+/// Hashes every input and returns the indices `(earlier, later)` of the first
+/// pair with the same digest, or `None` if all digests are distinct. Identical
+/// inputs count as a collision.
+#[cfg(feature = "std")]
+pub fn find_collision(inputs: &[&[u8]]) -> Option<(usize, usize)> {
+    let mut seen: HashMap<[u8; 16], usize> = HashMap::with_capacity(inputs.len());
+    
+    for (i, input) in inputs.iter().enumerate() {
+        match seen.entry(SMCHash::hash(input)) {
+            Entry::Occupied(first) => return Some((*first.get(), i)),
+            Entry::Vacant(slot) => {
+                slot.insert(i);
+            }
+        }
+    }
+    
+    None
+}
+
+/// Commits to `value` as `hash(salt || value)`. The salt keeps low-entropy
+/// values from being recovered by hashing guesses; keep it secret until the
+/// reveal.
+pub fn commit(value: &[u8], salt: &[u8; 16]) -> [u8; 16] {
+    let mut hasher = SMCHash::new();
+    hasher.update(salt);
+    hasher.update(value);
+    hasher.finalize()
+}
+
+/// Checks a revealed value and salt against a commitment in constant time
+pub fn verify_reveal(commitment: &[u8; 16], value: &[u8], salt: &[u8; 16]) -> bool {
+    ct_eq(&commit(value, salt), commitment)
+}
+
+/// Fingerprints `data` under a per-tenant `salt`, so identical data stored
+/// by different tenants gets unrelated digests and cannot be matched up.
+///
+/// The salt fills its own block (zero-padded) compressed from a dedicated
+/// IV before any data, so no (salt, data) pair collides with another pair
+/// or with an unsalted `hash` by shifting bytes between the two.
+pub fn hash_salted(salt: &[u8; 16], data: &[u8]) -> [u8; 16] {
+    let mut hasher = SMCHash::new_salted(salt);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Checks a digest from `hash_salted` in constant time
+pub fn verify_salted(salt: &[u8; 16], data: &[u8], expected: &[u8; 16]) -> bool {
+    ct_eq(&hash_salted(salt, data), expected)
+}
+
+/// Splits `data` into content-defined chunks and returns `(offset, hash)` for
+/// each, in order. Boundaries depend only on nearby bytes, so an insertion
+/// only changes the chunks around it, which makes the hashes useful for
+/// deduplication.
+///
+/// A gear-style rolling checksum over roughly the last 64 bytes is updated
+/// per byte, and a boundary is cut after a byte where its top
+/// `log2(avg_size)` bits (rounded up) are all zero. Chunks are kept between `avg_size / 4`
+/// and `avg_size * 4` bytes (the last one may be shorter).
+///
+/// Panics if `avg_size` is 0.
+#[cfg(feature = "alloc")]
+pub fn content_defined_chunks(data: &[u8], avg_size: usize) -> Vec<(usize, [u8; 16])> {
+    assert!(avg_size > 0, "avg_size must be non-zero");
+    let mask_bits = avg_size.next_power_of_two().trailing_zeros();
+    let mask = (u64::MAX >> mask_bits) ^ u64::MAX;
+    let min_size = (avg_size / 4).max(1);
+    let max_size = avg_size.saturating_mul(4);
+    
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut rolling: u64 = 0;
+    
+    for (i, &byte) in data.iter().enumerate() {
+        rolling = (rolling << 1).wrapping_add(gear(byte));
+        let len = i + 1 - start;
+        
+        if (len >= min_size && rolling & mask == 0) || len >= max_size {
+            chunks.push((start, SMCHash::hash(&data[start..=i])));
+            start = i + 1;
+            rolling = 0;
+        }
+    }
+    
+    if start < data.len() {
+        chunks.push((start, SMCHash::hash(&data[start..])));
+    }
+    chunks
+}
+
+// Pseudo-random 64-bit value per byte (SplitMix64 finalizer) for the rolling checksum
+#[cfg(feature = "alloc")]
+fn gear(byte: u8) -> u64 {
+    let mut z = (byte as u64).wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Message authentication code for `data` under a 16-byte secret `key`
+///
+/// The keyed digest of `data` is hashed again under the same key, so a tag
+/// cannot be length-extended into a tag for a longer message.
+pub fn mac(key: &[u8; 16], data: &[u8]) -> [u8; 16] {
+    let mut inner = SMCHash::new_keyed(key);
+    inner.update(data);
+    let inner = inner.finalize();
+    
+    let mut outer = SMCHash::new_keyed(key);
+    outer.update(&inner);
+    outer.finalize()
+}
+
+/// Checks a tag produced by `mac` in constant time
+pub fn verify_mac(key: &[u8; 16], data: &[u8], tag: &[u8; 16]) -> bool {
+    ct_eq(&mac(key, data), tag)
+}
+
+/// Keyed hash (MAC) of `data` under a secret `key` of any length
+///
+/// Uses an envelope construction, `hash(k || hash(k || data))` where `k` is
+/// the key prefixed with its length, so the tag cannot be length-extended
+/// the way a plain `hash(key || data)` could.
+pub fn hash_keyed(key: &[u8], data: &[u8]) -> [u8; 16] {
+    let keyed = |hasher: &mut SMCHash| {
+        hasher.update(&(key.len() as u64).to_le_bytes());
+        hasher.update(key);
+    };
+    
+    let mut inner = SMCHash::new();
+    keyed(&mut inner);
+    inner.update(data);
+    let inner = inner.finalize();
+    
+    let mut outer = SMCHash::new();
+    keyed(&mut outer);
+    outer.update(&inner);
+    outer.finalize()
+}
+
+/// First `tag_len` bytes (1..=16) of `hash_keyed(key, data)`, for tagging
+/// short packets
+///
+/// Shorter tags trade security for size: an attacker guessing tags succeeds
+/// with probability 2^-(8 * tag_len) per attempt, so a 4-byte tag falls to
+/// about 4 billion forgery attempts. Below 8 bytes, make sure failed
+/// verifications are rate-limited or otherwise costly.
+///
+/// Panics if `tag_len` is 0 or greater than 16.
+#[cfg(feature = "alloc")]
+pub fn mac_truncated(key: &[u8], data: &[u8], tag_len: usize) -> Vec<u8> {
+    assert!((1..=16).contains(&tag_len), "tag_len must be between 1 and 16");
+    hash_keyed(key, data)[..tag_len].to_vec()
+}
+
+/// Verifies a tag from `mac_truncated` in constant time. The tag's length
+/// selects the truncation; lengths outside 1..=16 never verify.
+pub fn verify_mac_truncated(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    if !(1..=16).contains(&tag.len()) {
+        return false;
+    }
+    constant_time_eq(&hash_keyed(key, data)[..tag.len()], tag)
+}
+
+/// Iterated, salted password hash: starts from `hash(salt || password)` and
+/// then applies `digest = hash(digest || salt || password)` `iterations` times,
+/// so the cost of brute-forcing grows linearly with `iterations`.
+///
+/// This is much better than a single `hash`, but it is NOT a substitute for a
+/// memory-hard password hash such as Argon2. Use a unique random salt per
+/// password.
+pub fn hash_password(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 16] {
+    let mut hasher = SMCHash::new();
+    hasher.update(salt);
+    hasher.update(password);
+    let mut digest = hasher.finalize();
+    
+    for _ in 0..iterations {
+        let mut hasher = SMCHash::new();
+        hasher.update(&digest);
+        hasher.update(salt);
+        hasher.update(password);
+        digest = hasher.finalize();
+    }
+    
+    digest
+}
+
+/// Checks a password against a digest from `hash_password` in constant time
+pub fn verify_password(password: &[u8], salt: &[u8], iterations: u32, expected: &[u8; 16]) -> bool {
+    ct_eq(&hash_password(password, salt, iterations), expected)
+}
+
+/// Hashing state after a fixed prefix, such as a block header without its
+/// nonce
+///
+/// Holds the compression state after every whole 64-byte block of the
+/// prefix, plus the few leftover bytes. Hashing the prefix followed by a
+/// suffix from here costs only the leftover bytes, the suffix and the
+/// padding (one or two compressions), however long the prefix is. For a
+/// 10 KB block that is over 100x cheaper than hashing it from scratch for
+/// every nonce.
+#[derive(Clone)]
+pub struct Midstate {
+    hasher: SMCHash,
+}
+
+impl Midstate {
+    pub fn new(prefix: &[u8]) -> Self {
+        let mut hasher = SMCHash::new();
+        hasher.update(prefix);
+        Midstate { hasher }
+    }
+    
+    /// Length in bytes of the absorbed prefix
+    pub fn prefix_len(&self) -> u64 {
+        self.hasher.total_bytes
+    }
+    
+    /// Hash of the prefix followed by `suffix`
+    pub fn hash_suffix(&self, suffix: &[u8]) -> [u8; 16] {
+        let mut hasher = self.hasher.clone();
+        hasher.update(suffix);
+        hasher.finalize()
+    }
+    
+    /// Proof-of-work hash of the prefix with `nonce` appended little-endian
+    pub fn pow_hash(&self, nonce: u64) -> [u8; 16] {
+        self.hash_suffix(&nonce.to_le_bytes())
+    }
+    
+    /// A hasher that continues from the end of the prefix
+    pub fn hasher(&self) -> SMCHash {
+        self.hasher.clone()
+    }
+}
+
+/// Checks candidate nonces for a fixed header without re-hashing it each time
+///
+/// The header is absorbed once into a `Midstate`, so each check compresses
+/// only the final block or two holding the nonce and padding. This is the
+/// verification-side counterpart of `SMCHash::create_proof_of_work`.
+#[derive(Clone)]
+pub struct PowVerifier {
+    midstate: Midstate,
+}
+
+impl PowVerifier {
+    pub fn new(header: &[u8]) -> Self {
+        Self::from_midstate(Midstate::new(header))
+    }
+    
+    /// Verifier for the header absorbed by `midstate`
+    pub fn from_midstate(midstate: Midstate) -> Self {
+        PowVerifier { midstate }
+    }
+    
+    pub fn midstate(&self) -> &Midstate {
+        &self.midstate
+    }
+    
+    /// Hash of the header followed by `nonce`
+    pub fn hash(&self, nonce: u64) -> [u8; 16] {
+        self.midstate.pow_hash(nonce)
+    }
+    
+    /// Same result as `SMCHash::check_proof_of_work(header, nonce, difficulty)`
+    pub fn check(&self, nonce: u64, difficulty: u8) -> bool {
+        meets_difficulty(&self.hash(nonce), difficulty)
+    }
+    
+    /// The smallest nonce in `nonces` that meets `difficulty`, if any
+    ///
+    /// Nonces are hashed several at a time through the multi-lane (SIMD where
+    /// available) compression kernel, so this is much faster than calling
+    /// `check` in a loop.
+    pub fn find(&self, nonces: Range<u64>, difficulty: u8) -> Option<u64> {
+        self.find_with(nonces, |hash| meets_difficulty(hash, difficulty))
+    }
+    
+    /// Same as `find`, for a numeric target (see `meets_target`)
+    pub fn find_target(&self, nonces: Range<u64>, target: u128) -> Option<u64> {
+        self.find_with(nonces, |hash| meets_target(hash, target))
+    }
+    
+    /// Same as `find`, for any condition on the hash
+    pub fn find_with<P: Fn(&[u8; 16]) -> bool>(&self, nonces: Range<u64>, accept: P) -> Option<u64> {
+        let mut first = nonces.start;
+        while first < nonces.end {
+            let suffixes = core::array::from_fn(|lane| first.wrapping_add(lane as u64).to_le_bytes());
+            let hashes = self.midstate.hasher.finalize_lanes(&suffixes);
+            
+            let in_range = (nonces.end - first).min(LANES as u64) as usize;
+            if let Some(lane) = hashes[..in_range].iter().position(&accept) {
+                return Some(first + lane as u64);
+            }
+            first += in_range as u64;
+        }
+        None
+    }
+}
+
+/// A proof-of-work search backend: the CPU `Miner`, or `gpu::GpuMiner`
+/// with the `gpu` feature. Backends agree on which nonces are valid but not
+/// necessarily on which one they return.
+#[cfg(feature = "std")]
+pub trait MiningBackend {
+    /// Searches for a nonce meeting `difficulty`, returning it and its hash
+    fn mine(&self, data: &[u8], difficulty: u8) -> (u64, [u8; 16]);
+}
+
+/// Proof-of-work miner backed by a pool of worker threads that is created
+/// once and reused for every job, so a node mining block after block does
+/// not pay the thread spawn cost each time. Dropping the miner shuts the
+/// workers down.
+///
+/// Jobs either block the caller (`mine`) or run in the background (`start`),
+/// reporting through the `MinerEvent` channels handed out by `subscribe`.
+#[cfg(feature = "std")]
+pub struct Miner {
+    jobs: Vec<mpsc::Sender<Arc<MineJob>>>,
+    workers: Vec<JoinHandle<()>>,
+    shared: Arc<MinerShared>,
+    // The job started with `start` and its id, if any
+    current: Mutex<Option<(u64, Arc<MineJob>)>>,
+}
+
+/// Work for `Miner::start`: mine `data` at `difficulty`. `id` identifies
+/// the job in `MinerEvent`s.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MiningJob {
+    pub id: u64,
+    pub data: Vec<u8>,
+    pub difficulty: u8,
+}
+
+/// Notification from a `Miner` about its background job
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinerEvent {
+    /// The job with id `job_id` was solved; the miner is idle again
+    SolutionFound { job_id: u64, nonce: u64, hash: [u8; 16] },
+    /// `start` replaced the unsolved job `previous` with `current`
+    JobReplaced { previous: u64, current: u64 },
+}
+
+/// Counters of a `Miner` since it was created
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinerStats {
+    /// Nonces tried across all jobs and workers
+    pub attempts: u64,
+    /// Hashes per second of the whole pool while it had work, or 0 before
+    /// the first job
+    pub hashrate: f64,
+    pub uptime: Duration,
+}
+
+// State shared between the miner and its workers
+#[cfg(feature = "std")]
+struct MinerShared {
+    created: Instant,
+    attempts: AtomicU64,
+    // Summed over workers, so divided by the thread count for wall time
+    busy_nanos: AtomicU64,
+    subscribers: Mutex<Vec<mpsc::Sender<MinerEvent>>>,
+}
+
+#[cfg(feature = "std")]
+impl MinerShared {
+    fn emit(&self, event: MinerEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        subscribers.retain(|subscriber| subscriber.send(event).is_ok());
+    }
+}
+
+// One job, shared by every worker. Worker `i` of `n` searches the batches
+// starting at `i * BATCH_SIZE`, striding by `n * BATCH_SIZE`.
+#[cfg(feature = "std")]
+struct MineJob {
+    verifier: PowVerifier,
+    difficulty: u8,
+    // Set once the job is solved or cancelled; workers then move on
+    done: AtomicBool,
+    solution: SolutionSink,
+}
+
+// Where a job's solution goes: back to a blocked `mine` call, or out as a
+// `MinerEvent` for a background job
+#[cfg(feature = "std")]
+enum SolutionSink {
+    Caller(mpsc::Sender<(u64, [u8; 16])>),
+    Event { job_id: u64 },
+}
+
+#[cfg(feature = "std")]
+impl Miner {
+    const BATCH_SIZE: u64 = 1024;
+    
+    /// Spawns `threads` workers (at least one)
+    pub fn new(threads: usize) -> Self {
+        let threads = threads.max(1);
+        let shared = Arc::new(MinerShared {
+            created: Instant::now(),
+            attempts: AtomicU64::new(0),
+            busy_nanos: AtomicU64::new(0),
+            subscribers: Mutex::new(Vec::new()),
+        });
+        let mut jobs = Vec::with_capacity(threads);
+        let mut workers = Vec::with_capacity(threads);
+        
+        for worker in 0..threads as u64 {
+            let (sender, receiver) = mpsc::channel::<Arc<MineJob>>();
+            let stride = threads as u64 * Self::BATCH_SIZE;
+            let shared = Arc::clone(&shared);
+            jobs.push(sender);
+            workers.push(thread::spawn(move || {
+                for job in receiver {
+                    Self::search(&job, &shared, worker * Self::BATCH_SIZE, stride);
+                }
+            }));
+        }
+        
+        Miner { jobs, workers, shared, current: Mutex::new(None) }
+    }
+    
+    /// Number of worker threads in the pool
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+    
+    /// Mines `data` on the pool and returns the first solution any worker
+    /// finds. This is not necessarily the smallest valid nonce. Stops the
+    /// background job, if any, first.
+    pub fn mine(&self, data: &[u8], difficulty: u8) -> (u64, [u8; 16]) {
+        assert_difficulty_in_range(difficulty);
+        self.stop();
+        let (solution, found) = mpsc::channel();
+        let job = Arc::new(MineJob {
+            verifier: PowVerifier::new(data),
+            difficulty,
+            done: AtomicBool::new(false),
+            solution: SolutionSink::Caller(solution),
+        });
+        
+        self.dispatch(&job);
+        
+        // Only the workers hold the job from here on, so `recv` fails rather
+        // than hanging if they all run out of nonces
+        let stop = Arc::clone(&job);
+        drop(job);
+        let result = found.recv().expect("no nonce meets the difficulty");
+        stop.done.store(true, AtomicOrdering::Release);
+        result
+    }
+    
+    /// Starts mining `job` in the background and returns immediately. The
+    /// solution is reported as `MinerEvent::SolutionFound`. An unsolved job
+    /// still running is abandoned, with a `MinerEvent::JobReplaced`.
+    pub fn start(&self, job: MiningJob) {
+        assert_difficulty_in_range(job.difficulty);
+        let next = Arc::new(MineJob {
+            verifier: PowVerifier::new(&job.data),
+            difficulty: job.difficulty,
+            done: AtomicBool::new(false),
+            solution: SolutionSink::Event { job_id: job.id },
+        });
+        
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((previous_id, previous)) = current.replace((job.id, Arc::clone(&next)))
+            && !previous.done.swap(true, AtomicOrdering::AcqRel)
+        {
+            self.shared.emit(MinerEvent::JobReplaced { previous: previous_id, current: job.id });
+        }
+        self.dispatch(&next);
+    }
+    
+    /// Abandons the background job, if any. Workers finish their current
+    /// batch of nonces and go idle.
+    pub fn stop(&self) {
+        let current = self.current.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some((_, job)) = current {
+            job.done.store(true, AtomicOrdering::Release);
+        }
+    }
+    
+    /// Returns a channel receiving every `MinerEvent` from now on. Dropping
+    /// the receiver unsubscribes.
+    pub fn subscribe(&self) -> mpsc::Receiver<MinerEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.shared.subscribers.lock().unwrap_or_else(|e| e.into_inner()).push(sender);
+        receiver
+    }
+    
+    /// Attempts, hashrate and uptime so far
+    pub fn stats(&self) -> MinerStats {
+        let attempts = self.shared.attempts.load(AtomicOrdering::Relaxed);
+        let busy_nanos = self.shared.busy_nanos.load(AtomicOrdering::Relaxed);
+        let busy_secs = busy_nanos as f64 / 1e9 / self.threads() as f64;
+        MinerStats {
+            attempts,
+            hashrate: if busy_nanos == 0 { 0.0 } else { attempts as f64 / busy_secs },
+            uptime: self.shared.created.elapsed(),
+        }
+    }
+    
+    fn dispatch(&self, job: &Arc<MineJob>) {
+        for sender in &self.jobs {
+            sender.send(Arc::clone(job)).expect("miner worker exited");
+        }
+    }
+    
+    fn search(job: &MineJob, shared: &MinerShared, mut batch_start: u64, stride: u64) {
+        while !job.done.load(AtomicOrdering::Acquire) {
+            let started = Instant::now();
+            let batch_end = batch_start.saturating_add(Self::BATCH_SIZE);
+            let found = job.verifier.find(batch_start..batch_end, job.difficulty);
+            
+            let tried = found.map_or(batch_end, |nonce| nonce + 1) - batch_start;
+            shared.attempts.fetch_add(tried, AtomicOrdering::Relaxed);
+            shared.busy_nanos.fetch_add(started.elapsed().as_nanos() as u64, AtomicOrdering::Relaxed);
+            
+            if let Some(nonce) = found {
+                // Only the first worker to solve the job reports it
+                if !job.done.swap(true, AtomicOrdering::AcqRel) {
+                    let hash = job.verifier.hash(nonce);
+                    match &job.solution {
+                        // The receiver is gone if the caller panicked
+                        SolutionSink::Caller(sender) => {
+                            let _ = sender.send((nonce, hash));
+                        }
+                        SolutionSink::Event { job_id } => {
+                            shared.emit(MinerEvent::SolutionFound { job_id: *job_id, nonce, hash });
+                        }
+                    }
+                }
+                return;
+            }
+            
+            batch_start = match batch_start.checked_add(stride) {
+                Some(next) => next,
+                None => return,
+            };
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl MiningBackend for Miner {
+    fn mine(&self, data: &[u8], difficulty: u8) -> (u64, [u8; 16]) {
+        Miner::mine(self, data, difficulty)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Miner {
+    fn drop(&mut self) {
+        self.stop();
+        // Closing the job channels ends each worker's receive loop
+        self.jobs.clear();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Lazily hashes `reader` in fixed-size chunks, yielding the SMCHash of each
+/// chunk as it is read. The final chunk may be shorter than `chunk_size`.
+/// Iteration stops after the first read error.
+///
+/// Panics if `chunk_size` is 0.
+#[cfg(feature = "std")]
+pub fn chunk_hashes<R: Read>(reader: R, chunk_size: usize) -> impl Iterator<Item = io::Result<[u8; 16]>> {
+    assert!(chunk_size > 0, "chunk_size must be non-zero");
+    ChunkHashes {
+        reader,
+        chunk: vec![0u8; chunk_size],
+        done: false,
+    }
+}
+
+#[cfg(feature = "std")]
+struct ChunkHashes<R> {
+    reader: R,
+    chunk: Vec<u8>,
+    done: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Iterator for ChunkHashes<R> {
+    type Item = io::Result<[u8; 16]>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        
+        // Fill the chunk completely unless the reader runs dry
+        let mut filled = 0;
+        while filled < self.chunk.len() {
+            match self.reader.read(&mut self.chunk[filled..]) {
+                Ok(0) => {
+                    self.done = true;
+                    break;
+                }
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        
+        if filled == 0 {
+            return None;
+        }
+        Some(Ok(SMCHash::hash(&self.chunk[..filled])))
+    }
+}
+
+/// Computes the Merkle root of a list of leaf hashes (e.g. txids)
+///
+/// Each level hashes adjacent pairs as `left || right` under
+/// `DOMAIN_MERKLE_NODE`, so a node never collides with a plain hash of the
+/// same 32 bytes (such as a txid or block data). An odd node at
+/// the end of a level is carried up unchanged rather than duplicated. The
+/// root of a single leaf is the leaf itself and the root of no leaves is all
+/// zeros.
+#[cfg(feature = "alloc")]
+pub fn merkle_root(leaves: &[[u8; 16]]) -> [u8; 16] {
+    if leaves.is_empty() {
+        return [0; 16];
+    }
+    
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level.chunks(2).map(merkle_pair).collect();
+    }
+    level[0]
+}
+
+/// Same as `merkle_root`, hashing the pairs of each level in parallel
+#[cfg(feature = "rayon")]
+pub fn merkle_root_parallel(leaves: &[[u8; 16]]) -> [u8; 16] {
+    use rayon::prelude::*;
+    
+    if leaves.is_empty() {
+        return [0; 16];
+    }
+    
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level.par_chunks(2).map(merkle_pair).collect();
+    }
+    level[0]
+}
+
+/// Tree hash of `data`, hashing `chunk_size`-byte chunks in parallel
+///
+/// The result is fully determined by `data` and `chunk_size`, regardless of
+/// thread count, so other nodes can reproduce it:
+///
+/// - leaf `i` is `hash(0x00 || i as u64 LE || chunk_i)`; empty data is a
+///   single empty chunk
+/// - each level hashes adjacent pairs as `hash(0x01 || left || right)`, an
+///   odd node at the end being carried up unchanged (as in `merkle_root`)
+/// - the result is `hash(0x02 || data.len() as u64 LE || chunk_size as u64 LE
+///   || tree root)`
+///
+/// The prefixes keep leaves, inner nodes and the root from colliding with
+/// one another or with a plain `hash` of the same bytes. Different chunk
+/// sizes give different results.
+///
+/// Panics if `chunk_size` is zero.
+#[cfg(feature = "rayon")]
+pub fn tree_hash(data: &[u8], chunk_size: usize) -> [u8; 16] {
+    use rayon::prelude::*;
+    
+    assert!(chunk_size > 0, "tree_hash chunk size must be non-zero");
+    
+    let mut level: Vec<[u8; 16]> = if data.is_empty() {
+        vec![tree_leaf(0, &[])]
+    } else {
+        data.par_chunks(chunk_size)
+            .enumerate()
+            .map(|(i, chunk)| tree_leaf(i as u64, chunk))
+            .collect()
+    };
+    while level.len() > 1 {
+        level = level
+            .par_chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = SMCHash::new();
+                    hasher.update(&[TREE_NODE]);
+                    hasher.update(left);
+                    hasher.update(right);
+                    hasher.finalize()
+                }
+                [single] => *single,
+                _ => unreachable!("tree levels are split into pairs"),
+            })
+            .collect();
+    }
+    
+    let mut hasher = SMCHash::new();
+    hasher.update(&[TREE_ROOT]);
+    hasher.update(&(data.len() as u64).to_le_bytes());
+    hasher.update(&(chunk_size as u64).to_le_bytes());
+    hasher.update(&level[0]);
+    hasher.finalize()
+}
+
+// Domain prefixes for `tree_hash` nodes
+#[cfg(feature = "rayon")]
+const TREE_LEAF: u8 = 0x00;
+#[cfg(feature = "rayon")]
+const TREE_NODE: u8 = 0x01;
+#[cfg(feature = "rayon")]
+const TREE_ROOT: u8 = 0x02;
+
+#[cfg(feature = "rayon")]
+fn tree_leaf(index: u64, chunk: &[u8]) -> [u8; 16] {
+    let mut hasher = SMCHash::new();
+    hasher.update(&[TREE_LEAF]);
+    hasher.update(&index.to_le_bytes());
+    hasher.update(chunk);
+    hasher.finalize()
+}
+
+// Hashes a pair of sibling nodes, carrying a lone node up unchanged
+#[cfg(feature = "alloc")]
+fn merkle_pair(pair: &[[u8; 16]]) -> [u8; 16] {
+    match pair {
+        [left, right] => merkle_node(left, right),
+        [single] => *single,
+        _ => unreachable!("merkle levels are split into pairs"),
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn merkle_node(left: &[u8; 16], right: &[u8; 16]) -> [u8; 16] {
+    let mut hasher = SMCHash::new_with_personalization(DOMAIN_MERKLE_NODE);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize()
+}
+
+/// Merkle root maintained incrementally as leaves arrive
+///
+/// Keeps one perfect subtree root per set bit of the leaf count (a "mountain
+/// range"), so `push` costs O(log n). `root()` always equals `merkle_root`
+/// over every leaf pushed so far.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default)]
+pub struct IncrementalMerkle {
+    // peaks[h] holds the root of a perfect subtree of 2^h leaves, if any
+    peaks: Vec<Option<[u8; 16]>>,
+    len: u64,
+}
+
+#[cfg(feature = "alloc")]
+impl IncrementalMerkle {
+    pub fn new() -> Self {
+        IncrementalMerkle { peaks: Vec::new(), len: 0 }
+    }
+    
+    /// Number of leaves pushed so far
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    
+    /// Appends a leaf, merging equal-sized subtrees like a binary counter
+    pub fn push(&mut self, leaf: [u8; 16]) {
+        let mut carry = leaf;
+        let mut height = 0;
+        
+        while let Some(Some(peak)) = self.peaks.get(height) {
+            carry = merkle_node(peak, &carry);
+            self.peaks[height] = None;
+            height += 1;
+        }
+        
+        if height == self.peaks.len() {
+            self.peaks.push(Some(carry));
+        } else {
+            self.peaks[height] = Some(carry);
+        }
+        self.len += 1;
+    }
+    
+    /// Current Merkle root, folding the peaks from the smallest (rightmost) up
+    pub fn root(&self) -> [u8; 16] {
+        let mut root: Option<[u8; 16]> = None;
+        for peak in self.peaks.iter().flatten() {
+            root = Some(match root {
+                None => *peak,
+                Some(right) => merkle_node(peak, &right),
+            });
+        }
+        root.unwrap_or([0; 16])
+    }
+}
+
+/// Encodes a 128-bit big-endian target into a compact 32-bit "bits" value,
+/// similar to Bitcoin's nBits: the top byte is the target's length in bytes
+/// (the exponent) and the low three bytes are its most significant bytes
+/// (the mantissa), so `target = mantissa * 256^(exponent - 3)`.
+///
+/// Unlike Bitcoin there is no sign bit, as targets are unsigned. Encoding
+/// keeps the three most significant bytes and truncates the rest, so the
+/// decoded target is never larger (easier) than the original. The canonical
+/// form has the smallest exponent that fits the target, which means the
+/// mantissa's top byte is non-zero unless the target is zero (encoded as 0).
+pub fn target_to_compact(target: &[u8; 16]) -> u32 {
+    let value = hash_to_u128(target);
+    let size = (128 - value.leading_zeros()).div_ceil(8);
+    
+    let mantissa = if size <= 3 {
+        (value << (8 * (3 - size))) as u32
+    } else {
+        (value >> (8 * (size - 3))) as u32
+    };
+    
+    (size << 24) | mantissa
+}
+
+/// Decodes a compact "bits" value produced by `target_to_compact`.
+///
+/// Non-canonical encodings decode to the value they describe. Exponents so
+/// large that the target would not fit in 128 bits saturate to the maximum
+/// target (all bytes 0xFF).
+pub fn compact_to_target(bits: u32) -> [u8; 16] {
+    let size = bits >> 24;
+    let mantissa = (bits & 0x00FF_FFFF) as u128;
+    
+    let value = if size <= 3 {
+        mantissa >> (8 * (3 - size))
+    } else {
+        let shift = 8 * (size - 3);
+        if mantissa != 0 && (shift >= 128 || mantissa.leading_zeros() < shift) {
+            u128::MAX
+        } else {
+            mantissa.checked_shl(shift).unwrap_or(0)
+        }
+    };
+    
+    u128_to_hash(value)
+}
+
+/// Target equivalent to a leading-zero-bit `difficulty`: hashes meet the
+/// target exactly when they have at least `difficulty` leading zero bits.
+/// For migrating from difficulty-based headers to targets.
+pub fn difficulty_to_target(difficulty: u8) -> [u8; 16] {
+    u128_to_hash(u128::MAX.checked_shr(difficulty as u32).unwrap_or(0))
+}
+
+/// Leading-zero-bit difficulty implied by `target`: every hash meeting the
+/// target also meets this difficulty. It is the target's own leading zero
+/// bits, so targets between two difficulties give the easier one; exact for
+/// targets from `difficulty_to_target`.
+pub fn target_to_difficulty(target: &[u8; 16]) -> u8 {
+    leading_zero_bits(target) as u8
+}
+
+/// Compact "bits" form of a leading-zero-bit difficulty. Encoding rounds
+/// the target down, but never past the next difficulty, so
+/// `compact_to_difficulty` recovers `difficulty` exactly.
+pub fn difficulty_to_compact(difficulty: u8) -> u32 {
+    target_to_compact(&difficulty_to_target(difficulty))
+}
+
+/// Leading-zero-bit difficulty of a compact target, see `target_to_difficulty`
+pub fn compact_to_difficulty(bits: u32) -> u8 {
+    target_to_difficulty(&compact_to_target(bits))
+}
+
+/// Builds the byte representation of a struct field by field, using the
+/// crate's little-endian encoding convention, and hashes it
+///
+/// Fields are concatenated without length prefixes, so variable-length
+/// fields should be placed where their length is implied by the layout.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default)]
+pub struct HashBuilder {
+    bytes: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl HashBuilder {
+    pub fn new() -> Self {
+        HashBuilder { bytes: Vec::new() }
+    }
+    
+    /// Appends raw bytes
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+    
+    /// Appends a 64-bit integer in little-endian order
+    pub fn push_u64(&mut self, value: u64) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+    
+    /// Appends a 16-byte hash
+    pub fn push_hash(&mut self, hash: &[u8; 16]) -> &mut Self {
+        self.bytes.extend_from_slice(hash);
+        self
+    }
+    
+    /// Returns the bytes pushed so far
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    
+    /// Consumes the builder and returns the encoded bytes
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+    
+    /// Hashes the bytes pushed so far
+    pub fn finish(&self) -> [u8; 16] {
+        SMCHash::hash(&self.bytes)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    
+    #[test]
+    fn test_empty_string() {
+        let hash = SMCHash::hash("".as_bytes());
+        // This is just a placeholder expected value - actual implementation would have its own value
+        assert_eq!(hash_to_hex(&hash).len(), 32); // 16 bytes = 32 hex chars
+    }
+    
+    #[test]
+    fn test_verification() {
+        let data = "test data for verification".as_bytes();
+        let hash = SMCHash::hash(data);
+        assert!(SMCHash::verify(data, &hash));
+        
+        // Modify data and ensure verification fails
+        let modified_data = "test data for verificationX".as_bytes();
+        assert!(!SMCHash::verify(modified_data, &hash));
+    }
+    
+    #[test]
+    fn test_proof_of_work() {
+        let data = "blockchain data".as_bytes();
+        let difficulty = 8; // 8 bits = 1 byte of leading zeros
+        
+        let (nonce, hash) = SMCHash::create_proof_of_work(data, difficulty);
+        assert!(SMCHash::verify_proof_of_work(data, nonce, difficulty, &hash));
+        
+        // Test first byte is zero (8 bits of difficulty)
+        assert_eq!(hash[0], 0);
+    }
+    
+    #[test]
+    fn test_check_proof_of_work() {
+        let data = "blockchain data".as_bytes();
+        let difficulty = 8;
+        
+        let (nonce, hash) = SMCHash::create_proof_of_work(data, difficulty);
+        assert!(SMCHash::check_proof_of_work(data, nonce, difficulty));
+        assert_eq!(hash[0], 0);
+        
+        // The same nonce should not satisfy a much harder difficulty
+        assert!(!SMCHash::check_proof_of_work(data, nonce, 64));
+    }
+    
+    #[test]
+    fn test_partial_byte_difficulty_checks_leading_bits() {
+        let data = "blockchain data".as_bytes();
+        let difficulty = 12;
+        
+        let (nonce, hash) = SMCHash::create_proof_of_work(data, difficulty);
+        assert_eq!(hash[0], 0);
+        assert_eq!(hash[1] & 0xF0, 0);
+        assert!(SMCHash::check_proof_of_work(data, nonce, difficulty));
+        assert!(SMCHash::verify_proof_of_work(data, nonce, difficulty, &hash));
+    }
+    
+    #[test]
+    fn test_different_inputs_produce_different_hashes() {
+        let hash1 = SMCHash::hash("input1".as_bytes());
+        let hash2 = SMCHash::hash("input2".as_bytes());
+        assert_ne!(hash1, hash2);
+    }
+    
+    #[test]
+    fn test_block_rejects_duplicate_transactions() {
+        let tx = Transaction::new([1; 16], [2; 16], 100, 0);
+        let result = Block::from_transactions(Hash16::ZERO, &[tx.clone(), tx.clone()], 12345, 4);
+        assert_eq!(result.unwrap_err(), SmcError::DuplicateTransaction(tx.txid()));
+    }
+    
+    #[test]
+    fn test_block_from_distinct_transactions() {
+        let tx1 = Transaction::new([1; 16], [2; 16], 100, 0);
+        let tx2 = Transaction::new([1; 16], [2; 16], 100, 1);
+        let block = Block::from_transactions(Hash16::ZERO, &[tx1.clone(), tx2.clone()], 12345, 4).unwrap();
+        assert!(block.validate(4));
+        assert_eq!(block.body.transactions, vec![tx1, tx2]);
+        assert!(block.body.matches(&block.header));
+    }
+    
+    #[test]
+    fn test_hash_builder_matches_block_header_bytes() {
+        let header = BlockHeader {
+            version: BlockHeader::VERSION,
+            prev_hash: Hash16([7; 16]),
+            merkle_root: Hash16([3; 16]),
+            timestamp: 12345,
+            bits: 0x0f00_ffff,
+            nonce: 9,
+        };
+        
+        let built = HashBuilder::new()
+            .push_bytes(&BlockHeader::VERSION.to_le_bytes())
+            .push_hash(&[7; 16])
+            .push_hash(&[3; 16])
+            .push_u64(12345)
+            .push_bytes(&0x0f00_ffffu32.to_le_bytes())
+            .push_u64(9)
+            .finish();
+        assert_eq!(built, SMCHash::hash(&header.to_bytes()));
+    }
+    
+    #[test]
+    fn test_difficulty_for_attempts() {
+        assert_eq!(difficulty_for_attempts(256), 8);
+        assert_eq!(difficulty_for_attempts(257), 8);
+        assert_eq!(difficulty_for_attempts(255), 7);
+        assert_eq!(difficulty_for_attempts(1), 0);
+        assert_eq!(difficulty_for_attempts(0), 0);
+        assert_eq!(difficulty_for_attempts(u64::MAX), 63);
+        assert!(difficulty_for_attempts(u64::MAX) <= 128);
+        
+        for difficulty in 0..64u8 {
+            let attempts = expected_attempts(difficulty) as u64;
+            assert_eq!(difficulty_for_attempts(attempts), difficulty);
+        }
+    }
+    
+    #[test]
+    fn test_zero_difficulty_accepts_first_nonce() {
+        let data = "blockchain data".as_bytes();
+        
+        let (nonce, hash) = SMCHash::create_proof_of_work(data, 0);
+        assert_eq!(nonce, 0);
+        assert_eq!(hash, SMCHash::pow_hash(data, 0));
+        assert!(SMCHash::verify_proof_of_work(data, nonce, 0, &hash));
+        assert!(SMCHash::check_proof_of_work(data, nonce, 0));
+        
+        // Any correctly computed hash qualifies, including one with a set leading bit
+        let nonce = (0..).find(|&n| SMCHash::pow_hash(data, n)[0] == 0xFF).unwrap();
+        assert!(SMCHash::check_proof_of_work(data, nonce, 0));
+        
+        // But the hash must still match
+        assert!(!SMCHash::verify_proof_of_work(data, nonce, 0, &[0; 16]));
+    }
+    
+    #[test]
+    fn test_error_variants() {
+        fn build(txs: &[Transaction]) -> Result<Block, Box<dyn std::error::Error>> {
+            Ok(Block::from_transactions(Hash16::ZERO, txs, 12345, 4)?)
+        }
+        
+        let tx = Transaction::new([1; 16], [2; 16], 100, 0);
+        let err = build(&[tx.clone(), tx.clone()]).unwrap_err();
+        match err.downcast_ref::<SmcError>() {
+            Some(SmcError::DuplicateTransaction(txid)) => assert_eq!(*txid, tx.txid()),
+            other => panic!("unexpected error: {:?}", other),
+        }
+        
+        assert!(err.to_string().starts_with("duplicate transaction"));
+        assert_eq!(SmcError::InvalidHex.to_string(), "invalid hex string");
+        assert_eq!(SmcError::ChecksumMismatch.to_string(), "checksum mismatch");
+    }
+    
+    #[test]
+    fn test_block_commits_to_transactions() {
+        let mut block = Block::new(Hash16::ZERO, sample_transactions(0), 12345, 8);
+        assert_eq!(block.hash(), block.header.hash());
+        assert!(block.validate(8));
+        
+        // Changing a transaction breaks the commitment even though the header
+        // still carries valid work
+        block.body.transactions[1].amount += 1;
+        assert!(block.header.validate());
+        assert!(!block.validate(8));
+        assert_eq!(block.achieved_difficulty(), 0);
+    }
+    
+    #[test]
+    fn test_large_update_matches_block_sized_updates() {
+        let data: Vec<u8> = (0..1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+        
+        let mut one_shot = SMCHash::new();
+        one_shot.update(&data);
+        
+        let mut chunked = SMCHash::new();
+        for chunk in data.chunks(64) {
+            chunked.update(chunk);
+        }
+        
+        // Unaligned chunk sizes exercise topping up a partial buffer
+        let mut uneven = SMCHash::new();
+        for chunk in data.chunks(100) {
+            uneven.update(chunk);
+        }
+        
+        let expected = one_shot.finalize();
+        assert_eq!(chunked.finalize(), expected);
+        assert_eq!(uneven.finalize(), expected);
+    }
+    
+    #[test]
+    fn test_compact_target_round_trip() {
+        let targets: [u128; 6] = [
+            1,
+            0x7F,
+            0x8000,
+            0x0012_3456,
+            0x00AB_CDEF << 64,
+            0xFFFF_FF00_0000_0000_0000_0000_0000_0000,
+        ];
+        
+        for &value in &targets {
+            let target = value.to_be_bytes();
+            let bits = target_to_compact(&target);
+            assert_eq!(compact_to_target(bits), target, "bits {:08x}", bits);
+        }
+        
+        assert_eq!(target_to_compact(&[0; 16]), 0);
+        assert_eq!(compact_to_target(0), [0; 16]);
+        assert_eq!(target_to_compact(&0x0012_3456u128.to_be_bytes()), 0x0312_3456);
+        assert_eq!(target_to_compact(&1u128.to_be_bytes()), 0x0101_0000);
+    }
+    
+    #[test]
+    fn test_compact_target_truncates_and_is_canonical() {
+        let value: u128 = 0x0123_4567_89AB_CDEF_0123_4567_89AB_CDEF;
+        let bits = target_to_compact(&value.to_be_bytes());
+        let decoded = u128::from_be_bytes(compact_to_target(bits));
+        
+        assert_eq!(bits, 0x1001_2345);
+        assert!(decoded <= value);
+        assert_eq!(decoded, 0x0123_4500_0000_0000_0000_0000_0000_0000);
+        assert_eq!(target_to_compact(&decoded.to_be_bytes()), bits);
+        
+        // Non-canonical encodings decode to the same value
+        assert_eq!(compact_to_target(0x0400_0012), compact_to_target(0x0212_0000));
+    }
+    
+    #[test]
+    fn test_compact_max_target() {
+        let bits = target_to_compact(&[0xFF; 16]);
+        assert_eq!(bits, 0x10FF_FFFF);
+        
+        let mut expected = [0u8; 16];
+        expected[..3].copy_from_slice(&[0xFF; 3]);
+        assert_eq!(compact_to_target(bits), expected);
+        
+        // Exponents past 16 bytes saturate instead of wrapping
+        assert_eq!(compact_to_target(0x11FF_FFFF), [0xFF; 16]);
+        assert_eq!(compact_to_target(0xFF00_0001), [0xFF; 16]);
+        assert_eq!(compact_to_target(0x1100_00FF), (0xFFu128 << 112).to_be_bytes());
+    }
+    
+    // A coinbase, its nonce set to `seed` as an extranonce, and a transfer
+    fn sample_transactions(seed: u64) -> Vec<Transaction> {
+        vec![Transaction::new([0; 16], [1; 16], 5000, seed), Transaction::new([1; 16], [2; 16], 100, 0)]
+    }
+    
+    fn build_chain(len: usize, difficulty: u8) -> Vec<Block> {
+        let mut chain: Vec<Block> = Vec::with_capacity(len);
+        for i in 0..len {
+            let prev_hash = chain.last().map_or(Hash16::ZERO, Block::hash);
+            chain.push(Block::new(prev_hash, sample_transactions(i as u64), 1000 + i as u64, difficulty));
+        }
+        chain
+    }
+    
+    #[test]
+    fn test_verify_chain_links() {
+        let chain = build_chain(4, 4);
+        assert_eq!(verify_chain_links(&chain, 4), Ok(()));
+        assert_eq!(verify_chain_links(&[], 4), Ok(()));
+        
+        // Broken link: block 2 is valid on its own but points elsewhere
+        let mut broken = build_chain(4, 4);
+        broken[2] = Block::new(Hash16([9; 16]), sample_transactions(2), 1002, 4);
+        assert_eq!(verify_chain_links(&broken, 4), Err((2, SmcError::PrevHashMismatch)));
+        
+        // Tampered transactions no longer match the header
+        let mut tampered = build_chain(4, 4);
+        tampered[1].body.transactions.pop();
+        assert_eq!(verify_chain_links(&tampered, 4), Err((1, SmcError::InvalidProofOfWork)));
+    }
+    
+    #[test]
+    fn test_hash_u128_conversions() {
+        assert_eq!(hash_to_u128(&[0; 16]), 0);
+        assert_eq!(hash_to_u128(&[0xFF; 16]), u128::MAX);
+        
+        let mut hash = [0u8; 16];
+        hash[15] = 1;
+        assert_eq!(hash_to_u128(&hash), 1);
+        
+        for value in [0, 1, 0x0123_4567_89AB_CDEF, u128::MAX >> 3, u128::MAX] {
+            assert_eq!(hash_to_u128(&u128_to_hash(value)), value);
+        }
+        let hash = SMCHash::hash(b"round trip");
+        assert_eq!(u128_to_hash(hash_to_u128(&hash)), hash);
+    }
+    
+    #[test]
+    fn test_proof_of_work_returns_lowest_nonce() {
+        let data = "blockchain data".as_bytes();
+        
+        for difficulty in [4, 8, 10] {
+            let (nonce, hash) = SMCHash::create_proof_of_work(data, difficulty);
+            assert!(SMCHash::verify_proof_of_work(data, nonce, difficulty, &hash));
+            
+            for smaller in 0..nonce {
+                assert!(
+                    !SMCHash::check_proof_of_work(data, smaller, difficulty),
+                    "nonce {} also solves difficulty {}", smaller, difficulty
+                );
+            }
+        }
+    }
+    
+    #[test]
+    fn test_hash_reader_with_progress() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 253) as u8).collect();
+        
+        let mut reports = Vec::new();
+        let hash = SMCHash::hash_reader_with_progress(std::io::Cursor::new(&data), |n| reports.push(n))
+            .unwrap();
+        
+        assert_eq!(hash, SMCHash::hash(&data));
+        assert!(reports.len() > 1);
+        assert!(reports.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(*reports.last().unwrap(), data.len() as u64);
+    }
+    
+    #[test]
+    fn test_block_achieved_difficulty() {
+        let mut block = Block::new(Hash16::ZERO, sample_transactions(0), 12345, 8);
+        assert!(block.achieved_difficulty() >= 8);
+        assert!(block.validate(block.achieved_difficulty() as u8));
+        assert!(!block.validate(block.achieved_difficulty() as u8 + 1));
+        
+        block.body.transactions.clear();
+        assert_eq!(block.achieved_difficulty(), 0);
+    }
+    
+    #[test]
+    fn test_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0; 16]), 128);
+        assert_eq!(leading_zero_bits(&[0xFF; 16]), 0);
+        
+        let mut hash = [0u8; 16];
+        hash[1] = 0x10;
+        assert_eq!(leading_zero_bits(&hash), 11);
+    }
+    
+    #[test]
+    fn test_chunk_hashes() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 7) as u8).collect();
+        
+        let hashes: Vec<[u8; 16]> = chunk_hashes(std::io::Cursor::new(&data), 256)
+            .collect::<std::io::Result<_>>()
+            .unwrap();
+        let expected: Vec<[u8; 16]> = data.chunks(256).map(SMCHash::hash).collect();
+        
+        assert_eq!(hashes.len(), 4);
+        assert_eq!(hashes, expected);
+        assert_eq!(chunk_hashes(std::io::Cursor::new(&[]), 256).count(), 0);
+    }
+    
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"same bytes", b"same bytes"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"same bytes", b"same bytez"));
+        assert!(!constant_time_eq(b"xame bytes", b"same bytes"));
+        assert!(!constant_time_eq(b"short", b"shorter"));
+        assert!(!constant_time_eq(b"shorter", b"short"));
+    }
+    
+    #[test]
+    fn test_block_commits_to_miner() {
+        let pay = |miner: [u8; 16]| vec![Transaction::new([0; 16], miner, 5000, 0)];
+        let a = Block::new(Hash16::ZERO, pay([1; 16]), 12345, 4);
+        let b = Block::new(Hash16::ZERO, pay([2; 16]), 12345, 4);
+        
+        assert_ne!(a.hash(), b.hash());
+        assert!(a.validate(4));
+        assert!(b.validate(4));
+        
+        // Redirecting the coinbase of a mined block invalidates it
+        let mut stolen = a;
+        stolen.body.transactions[0].to = [2; 16];
+        assert!(!stolen.validate(4));
+    }
+    
+    #[test]
+    fn test_mine_k_solutions() {
+        let data = "blockchain data".as_bytes();
+        let solutions = SMCHash::mine_k_solutions(data, 4, 3);
+        
+        assert_eq!(solutions.len(), 3);
+        assert!(solutions.windows(2).all(|w| w[0].0 < w[1].0));
+        for (nonce, hash) in &solutions {
+            assert!(SMCHash::verify_proof_of_work(data, *nonce, 4, hash));
+        }
+        assert_eq!(solutions[0], SMCHash::create_proof_of_work(data, 4));
+        assert!(SMCHash::mine_k_solutions(data, 4, 0).is_empty());
+    }
+    
+    #[test]
+    fn test_merkle_root_small_trees() {
+        let a = SMCHash::hash(b"a");
+        let b = SMCHash::hash(b"b");
+        let c = SMCHash::hash(b"c");
+        
+        assert_eq!(merkle_root(&[]), [0; 16]);
+        assert_eq!(merkle_root(&[a]), a);
+        assert_eq!(merkle_root(&[a, b]), merkle_node(&a, &b));
+        assert_ne!(merkle_node(&a, &b), SMCHash::hash(&[a, b].concat()));
+        assert_eq!(merkle_node(&a, &b), SMCHash::hash_personalized(DOMAIN_MERKLE_NODE, &[a, b].concat()));
+        assert_eq!(merkle_root(&[a, b, c]), merkle_node(&merkle_node(&a, &b), &c));
+    }
+    
+    #[test]
+    fn test_incremental_merkle_matches_merkle_root() {
+        let leaves: Vec<[u8; 16]> = (1..=10u64).map(|i| SMCHash::hash(&i.to_le_bytes())).collect();
+        
+        let mut tree = IncrementalMerkle::new();
+        assert_eq!(tree.root(), merkle_root(&[]));
+        
+        for (i, leaf) in leaves.iter().enumerate() {
+            tree.push(*leaf);
+            assert_eq!(tree.len(), i as u64 + 1);
+            assert_eq!(tree.root(), merkle_root(&leaves[..=i]), "after {} leaves", i + 1);
+        }
+    }
+    
+    #[test]
+    fn test_empty_input_vector() {
+        // Pinned so any change to padding or the compression function is caught
+        assert_eq!(hash_to_hex(&SMCHash::hash(b"")), "00c80a553f1fff3620b0c2480b083c10");
+        assert_eq!(SMCHash::hash(b""), SMCHash::new().finalize());
+    }
+    
+    #[test]
+    fn test_proof_of_work_over_empty_data() {
+        // Only the 8 nonce bytes are hashed, a single padded block
+        let (nonce, hash) = SMCHash::create_proof_of_work(b"", 8);
+        assert_eq!(nonce, 108);
+        assert_eq!(hash, SMCHash::hash(&nonce.to_le_bytes()));
+        assert!(SMCHash::verify_proof_of_work(b"", nonce, 8, &hash));
+        assert!(SMCHash::check_proof_of_work(b"", nonce, 8));
+    }
+    
+    #[test]
+    fn test_hash_of_accepts_byte_like_inputs() {
+        let expected = SMCHash::hash(b"abc");
+        
+        assert_eq!(SMCHash::hash_of("abc"), expected);
+        assert_eq!(SMCHash::hash_of(String::from("abc")), expected);
+        assert_eq!(SMCHash::hash_of(vec![b'a', b'b', b'c']), expected);
+        assert_eq!(SMCHash::hash_of(*b"abc"), expected);
+        assert_eq!(SMCHash::hash_of([0u8; 4]), SMCHash::hash(&[0, 0, 0, 0]));
+    }
+    
+    #[test]
+    fn test_hash_password() {
+        let salt = b"per-user salt";
+        let stored = hash_password(b"hunter2", salt, 1000);
+        
+        assert_ne!(stored, hash_password(b"hunter2", salt, 1001));
+        assert_ne!(stored, hash_password(b"hunter2", b"other salt", 1000));
+        assert_eq!(hash_password(b"hunter2", salt, 0), SMCHash::hash(b"per-user salthunter2"));
+        
+        assert!(verify_password(b"hunter2", salt, 1000, &stored));
+        assert!(!verify_password(b"hunter3", salt, 1000, &stored));
+        assert!(!verify_password(b"hunter2", salt, 999, &stored));
+    }
+    
+    #[test]
+    fn test_mine_lowest() {
+        let data = "blockchain data".as_bytes();
+        let (nonce, hash) = SMCHash::mine_lowest(data, std::time::Duration::from_millis(20));
+        
+        assert_eq!(hash, SMCHash::pow_hash(data, nonce));
+        assert!(hash_to_u128(&hash) <= hash_to_u128(&SMCHash::pow_hash(data, 0)));
+        
+        // A zero budget still evaluates a full batch of nonces
+        let (_, quick) = SMCHash::mine_lowest(data, std::time::Duration::ZERO);
+        assert!(hash_to_u128(&quick) <= hash_to_u128(&SMCHash::pow_hash(data, 0)));
+    }
+    
+    #[test]
+    fn test_debug_state_after_one_block() {
+        let mut hasher = SMCHash::new();
+        assert_eq!(hasher.debug_state(), [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a]);
+        
+        let block: Vec<u8> = (0..64u8).collect();
+        hasher.update(&block);
+        
+        // Pinned midstate: catches any accidental change to process_block
+        assert_eq!(hasher.debug_state(), [0x7beb03ad, 0x2c8d4364, 0xe6eccf13, 0xfd596a69]);
+    }
+    
+    #[test]
+    fn test_block_bytes_round_trip() {
+        let block = Block::new(Hash16([5; 16]), sample_transactions(6), 12345, 4);
+        let bytes = block.to_bytes();
+        assert_eq!(bytes.len(), 1 + BlockHeader::SERIALIZED_LEN + 4 + 2 * Transaction::SERIALIZED_LEN);
+        
+        let decoded = Block::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, block);
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert!(decoded.validate(4));
+        
+        let empty = Block::new(Hash16([5; 16]), Vec::new(), 12345, 4);
+        assert_eq!(Block::from_bytes(&empty.to_bytes()).unwrap(), empty);
+    }
+    
+    #[test]
+    fn test_block_from_bytes_rejects_trailing_and_truncated_input() {
+        let block = Block::new(Hash16([5; 16]), sample_transactions(0), 12345, 4);
+        let mut bytes = block.to_bytes();
+        
+        assert!(Block::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert_eq!(Block::from_bytes(&bytes[..10]).unwrap_err(), SmcError::DecodeTruncated);
+        
+        bytes.push(0);
+        assert_eq!(Block::from_bytes(&bytes).unwrap_err(), SmcError::TrailingBytes);
+    }
+    
+    #[test]
+    fn test_proof_of_work_with_progress() {
+        let data = "blockchain data".as_bytes();
+        let difficulty = 16;
+        
+        let mut reports = Vec::new();
+        let (nonce, hash) =
+            SMCHash::create_proof_of_work_with_progress(data, difficulty, |n| reports.push(n));
+        
+        assert_eq!((nonce, hash), SMCHash::create_proof_of_work(data, difficulty));
+        assert_eq!(reports.len() as u64, nonce / PROGRESS_INTERVAL);
+        assert!(reports.iter().enumerate().all(|(i, &n)| n == (i as u64 + 1) * PROGRESS_INTERVAL));
+    }
+    
+    #[test]
+    fn test_proof_of_work_with_callback() {
+        let data = b"blockchain data";
+        let expected = SMCHash::create_proof_of_work(data, 16);
+        
+        for interval in [1000, PROGRESS_INTERVAL] {
+            let mut reports = Vec::new();
+            let report = |attempts, rate: f64| {
+                assert!(rate.is_finite() && rate >= 0.0);
+                reports.push(attempts);
+                ControlFlow::Continue(())
+            };
+            let found = SMCHash::create_proof_of_work_with_callback(data, 16, interval, report);
+            assert_eq!(found, Some(expected));
+            assert_eq!(reports.len() as u64, expected.0 / interval);
+            assert!(reports.iter().enumerate().all(|(i, &n)| n == (i as u64 + 1) * interval));
+        }
+        
+        // Breaking aborts at the next report
+        let mut calls = 0;
+        let report = |attempts, _| {
+            calls += 1;
+            if attempts >= 300 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+        };
+        let aborted = SMCHash::create_proof_of_work_with_callback(data, MAX_DIFFICULTY, 100, report);
+        assert_eq!(aborted, None);
+        assert_eq!(calls, 3);
+    }
+    
+    #[test]
+    #[should_panic(expected = "progress interval must be non-zero")]
+    fn test_proof_of_work_with_callback_rejects_zero_interval() {
+        let report = |_, _| ControlFlow::Continue(());
+        SMCHash::create_proof_of_work_with_callback(b"data", 8, 0, report);
+    }
+    
+    #[test]
+    fn test_block_equality() {
+        let a = Block::new(Hash16::ZERO, sample_transactions(0), 12345, 4);
+        let b = Block::new(Hash16::ZERO, sample_transactions(0), 12345, 4);
+        assert_eq!(a, b);
+        
+        let mut c = b.clone();
+        c.header.timestamp += 1;
+        assert_ne!(a, c);
+        
+        let set: HashSet<Block> = [a.clone(), b, c].into_iter().collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&a));
+    }
+    
+    #[test]
+    fn test_select_transactions() {
+        let pool: Vec<Transaction> = [300, 100, 500, 200, 400]
+            .iter()
+            .enumerate()
+            .map(|(i, &amount)| Transaction::new([1; 16], [2; 16], amount, i as u64))
+            .collect();
+        
+        let selected = select_transactions(&pool, 4);
+        let amounts: Vec<u64> = selected.iter().map(|tx| tx.amount).collect();
+        assert_eq!(amounts, vec![500, 400, 300]);
+        
+        assert_eq!(select_transactions(&pool, 100).len(), pool.len());
+        assert!(select_transactions(&pool, 1).is_empty());
+        assert!(select_transactions(&pool, 0).is_empty());
+    }
+    
+    #[test]
+    fn test_select_transactions_skips_coinbase_and_duplicates() {
+        let tx = Transaction::new([1; 16], [2; 16], 100, 0);
+        let coinbase = Transaction::new([0; 16], [2; 16], 5000, 0);
+        let pool = vec![tx.clone(), coinbase, tx.clone()];
+        
+        let selected = select_transactions(&pool, 10);
+        assert_eq!(selected, vec![tx.clone()]);
+        assert!(Block::from_transactions(Hash16::ZERO, &selected, 12345, 4).is_ok());
+    }
+    
+    #[test]
+    fn test_length_suffix_near_u64_bit_boundary() {
+        let state = SMCHash::new().debug_state();
+        
+        // One block short of 2^61 bytes, so the bit length is just below 2^64
+        let total_before = (1u64 << 61) - 64;
+        let mut hasher = SMCHash::from_midstate(state, total_before);
+        hasher.update(&[0xAB; 8]);
+        
+        let bit_len = (total_before + 8).wrapping_mul(8);
+        assert_eq!(bit_len, u64::MAX - 447);
+        let mut last_block = [0u8; 64];
+        last_block[..8].copy_from_slice(&[0xAB; 8]);
+        last_block[8] = 0x80;
+        last_block[56..].copy_from_slice(&bit_len.to_le_bytes());
+        
+        let mut expected = SMCHash::from_midstate(state, 0);
+        expected.process_block(&last_block);
+        let expected: Vec<u8> = expected.debug_state().iter().flat_map(|w| w.to_le_bytes()).collect();
+        assert_eq!(hasher.finalize().to_vec(), expected);
+        
+        // At exactly 2^61 bytes the bit length wraps to 0
+        let wrapped = SMCHash::from_midstate(state, 1u64 << 61).finalize();
+        assert_eq!(wrapped, SMCHash::from_midstate(state, 0).finalize());
+    }
+    
+    #[test]
+    fn test_common_ancestor() {
+        let chain = build_chain(5, 4);
+        
+        // Fork after block 2
+        let mut fork = chain[..3].to_vec();
+        fork.push(Block::new(fork[2].hash(), sample_transactions(10), 2000, 4));
+        fork.push(Block::new(fork[3].hash(), sample_transactions(11), 2001, 4));
+        
+        assert_eq!(common_ancestor(&chain, &fork), Some(2));
+        assert!(chains_agree_up_to(&chain, &fork, 2));
+        assert!(!chains_agree_up_to(&chain, &fork, 3));
+        
+        assert_eq!(common_ancestor(&chain, &chain), Some(4));
+        assert_eq!(common_ancestor(&chain, &chain[..3]), Some(2));
+        assert!(chains_agree_up_to(&chain, &chain, 4));
+        assert!(!chains_agree_up_to(&chain, &chain, 5));
+        
+        let other = vec![Block::new(Hash16([1; 16]), sample_transactions(0), 1000, 4)];
+        assert_eq!(common_ancestor(&chain, &other), None);
+        assert_eq!(common_ancestor(&chain, &[]), None);
+    }
+    
+    #[test]
+    fn test_hash_fixed_matches_hash() {
+        let small = [7u8; 16];
+        let tx = [9u8; 48];
+        let block: [u8; 64] = std::array::from_fn(|i| i as u8);
+        let padding_edge: [u8; 56] = std::array::from_fn(|i| (i * 3) as u8);
+        let multi: [u8; 200] = std::array::from_fn(|i| (i * 5) as u8);
+        
+        assert_eq!(SMCHash::hash_fixed(&small), SMCHash::hash(&small));
+        assert_eq!(SMCHash::hash_fixed(&tx), SMCHash::hash(&tx));
+        assert_eq!(SMCHash::hash_fixed(&block), SMCHash::hash(&block));
+        assert_eq!(SMCHash::hash_fixed(&padding_edge), SMCHash::hash(&padding_edge));
+        assert_eq!(SMCHash::hash_fixed(&multi), SMCHash::hash(&multi));
+        assert_eq!(SMCHash::hash_fixed(&[]), SMCHash::hash(b""));
+    }
+    
+    #[test]
+    fn test_mine_block_rolling() {
+        let body = BlockBody::new(sample_transactions(0));
+        let (nonce, timestamp, hash) = mine_block_rolling(Hash16([1; 16]), &body, 8, 5000);
+        assert_eq!(timestamp, 5000);
+        
+        // The triple re-verifies against a header rebuilt from the body
+        let mut header = body.header(Hash16([1; 16]), timestamp, difficulty_to_compact(8));
+        header.nonce = nonce;
+        assert_eq!(header.hash(), hash);
+        let block = Block { header, body };
+        assert!(block.validate(8));
+        assert_eq!(block, Block::new(Hash16([1; 16]), sample_transactions(0), 5000, 8));
+    }
+    
+    #[test]
+    fn test_mine_block_rolling_bumps_timestamp() {
+        // With only 2 nonces per timestamp, difficulty 8 needs several rolls
+        let body = BlockBody::new(sample_transactions(0));
+        let header = mine_rolling_up_to(Hash16([1; 16]), &body, 8, 5000, 1);
+        
+        assert!(header.nonce <= 1);
+        assert!(header.timestamp > 5000);
+        assert!(Block { header, body }.validate(8));
+    }
+    
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_merkle_root_parallel_matches_sequential() {
+        let leaves: Vec<[u8; 16]> = (0..1000u32).map(|i| SMCHash::hash(&i.to_le_bytes())).collect();
+        assert_eq!(merkle_root_parallel(&leaves), merkle_root(&leaves));
+        
+        for len in 0..=33 {
+            assert_eq!(merkle_root_parallel(&leaves[..len]), merkle_root(&leaves[..len]), "{} leaves", len);
+        }
+    }
+    
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_tree_hash_matches_spec() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i * 7) as u8).collect();
+        
+        // Reference computation straight from the documented node format
+        let reference = |data: &[u8], chunk_size: usize| {
+            let mut level: Vec<[u8; 16]> = if data.is_empty() {
+                vec![SMCHash::hash(&[0; 9])]
+            } else {
+                data.chunks(chunk_size)
+                    .enumerate()
+                    .map(|(i, c)| SMCHash::hash(&[&[0u8][..], &(i as u64).to_le_bytes(), c].concat()))
+                    .collect()
+            };
+            while level.len() > 1 {
+                level = level
+                    .chunks(2)
+                    .map(|p| if p.len() == 2 { SMCHash::hash(&[&[1u8][..], &p[0], &p[1]].concat()) } else { p[0] })
+                    .collect();
+            }
+            let lengths = [(data.len() as u64).to_le_bytes(), (chunk_size as u64).to_le_bytes()].concat();
+            SMCHash::hash(&[&[2u8][..], &lengths, &level[0]].concat())
+        };
+        
+        for (len, chunk_size) in [(10_000, 1024), (10_000, 10_000), (10_000, 1), (1000, 333), (0, 64), (64, 64)] {
+            assert_eq!(tree_hash(&data[..len], chunk_size), reference(&data[..len], chunk_size), "{} / {}", len, chunk_size);
+        }
+        
+        assert_ne!(tree_hash(&data, 1024), tree_hash(&data, 2048));
+        assert_ne!(tree_hash(&data[..64], 64), SMCHash::hash(&data[..64]));
+    }
+    
+    #[test]
+    fn test_find_collision() {
+        let inputs: [&[u8]; 4] = [b"alpha", b"beta", b"gamma", b"beta"];
+        assert_eq!(find_collision(&inputs), Some((1, 3)));
+        
+        let distinct: Vec<[u8; 8]> = (0..10_000u64).map(|i| i.to_le_bytes()).collect();
+        let distinct: Vec<&[u8]> = distinct.iter().map(|b| b.as_slice()).collect();
+        assert_eq!(find_collision(&distinct), None);
+        assert_eq!(find_collision(&[]), None);
+    }
+    
+    #[test]
+    fn test_update_from_iter() {
+        let bytes: Vec<u8> = (0u8..200).collect();
+        
+        let mut hasher = SMCHash::new();
+        hasher.update_from_iter(0u8..200);
+        assert_eq!(hasher.finalize(), SMCHash::hash(&bytes));
+        
+        // Mixing with slice updates keeps the buffer consistent
+        let mut mixed = SMCHash::new();
+        mixed.update(&bytes[..10]);
+        mixed.update_from_iter(bytes[10..150].iter().copied()).update_from_iter(150u8..200);
+        assert_eq!(mixed.finalize(), SMCHash::hash(&bytes));
+    }
+    
+    #[test]
+    fn test_rotl_edge_cases() {
+        let x = 0x8000_0001;
+        assert_eq!(rotl(x, 0), x);
+        assert_eq!(rotl(x, 32), x);
+        assert_eq!(rotl(x, 1), 0x0000_0003);
+        assert_eq!(rotl(x, 33), rotl(x, 1));
+    }
+    
+    #[test]
+    fn test_pow_verifier_agrees_with_check_proof_of_work() {
+        let aligned: Vec<u8> = (0..128u32).map(|i| (i * 13) as u8).collect();
+        let unaligned = &aligned[..77];
+        
+        for header in [&aligned[..], unaligned] {
+            let verifier = PowVerifier::new(header);
+            let (solution, _) = SMCHash::create_proof_of_work(header, 8);
+            
+            for nonce in (0..50).chain([solution]) {
+                for difficulty in [0, 4, 8] {
+                    assert_eq!(
+                        verifier.check(nonce, difficulty),
+                        SMCHash::check_proof_of_work(header, nonce, difficulty)
+                    );
+                }
+            }
+            assert!(verifier.check(solution, 8));
+        }
+    }
+    
+    #[test]
+    fn test_cmp_hashes_sorts_big_endian() {
+        let mut low_byte_high = [0u8; 16];
+        low_byte_high[15] = 0xFF;
+        let mut high_byte_low = [0u8; 16];
+        high_byte_low[0] = 0x01;
+        
+        let mut hashes = vec![[0xFF; 16], high_byte_low, [0; 16], low_byte_high];
+        hashes.sort_by(cmp_hashes);
+        assert_eq!(hashes, vec![[0; 16], low_byte_high, high_byte_low, [0xFF; 16]]);
+        
+        assert_eq!(cmp_hashes(&low_byte_high, &low_byte_high), Ordering::Equal);
+        assert!(hashes.windows(2).all(|w| hash_to_u128(&w[0]) < hash_to_u128(&w[1])));
+    }
+    
+    #[test]
+    fn test_difficulty_at_and_above_maximum() {
+        let data = "blockchain data".as_bytes();
+        let (nonce, hash) = SMCHash::create_proof_of_work(data, 8);
+        
+        // Only an all-zero hash meets the maximum difficulty
+        assert!(meets_difficulty(&[0; 16], MAX_DIFFICULTY));
+        assert!(!meets_difficulty(&u128_to_hash(1), MAX_DIFFICULTY));
+        assert!(!SMCHash::check_proof_of_work(data, nonce, 128));
+        assert!(!SMCHash::verify_proof_of_work(data, nonce, 128, &hash));
+        
+        // Out-of-range difficulties are rejected without indexing past the hash
+        assert!(!meets_difficulty(&[0; 16], 129));
+        assert!(!SMCHash::check_proof_of_work(data, nonce, 200));
+        assert!(!SMCHash::verify_proof_of_work(data, nonce, 255, &hash));
+    }
+    
+    #[test]
+    #[should_panic(expected = "exceeds the maximum")]
+    fn test_mining_above_maximum_difficulty_panics() {
+        SMCHash::create_proof_of_work(b"data", 200);
+    }
+    
+    #[test]
+    fn test_commit_reveal() {
+        let salt = [42u8; 16];
+        let commitment = commit(b"heads", &salt);
+        
+        assert!(verify_reveal(&commitment, b"heads", &salt));
+        assert!(!verify_reveal(&commitment, b"tails", &salt));
+        assert!(!verify_reveal(&commitment, b"heads", &[43u8; 16]));
+        assert_ne!(commitment, SMCHash::hash(b"heads"));
+    }
+    
+    #[test]
+    fn test_content_defined_chunks() {
+        let data: Vec<u8> = (0..20_000u32).map(|i| SMCHash::hash(&i.to_le_bytes())[0]).collect();
+        let chunks = content_defined_chunks(&data, 256);
+        
+        // Chunks tile the input exactly and hash their own ranges
+        assert_eq!(chunks[0].0, 0);
+        let mut rebuilt = Vec::new();
+        for (i, &(offset, hash)) in chunks.iter().enumerate() {
+            let end = chunks.get(i + 1).map_or(data.len(), |next| next.0);
+            assert!(end > offset);
+            assert!(end - offset <= 1024);
+            assert_eq!(hash, SMCHash::hash(&data[offset..end]));
+            rebuilt.extend_from_slice(&data[offset..end]);
+        }
+        assert_eq!(rebuilt, data);
+        assert!(chunks.len() > 20 && chunks.len() < 200);
+        
+        // Deterministic for the same input and size
+        assert_eq!(content_defined_chunks(&data, 256), chunks);
+        assert!(content_defined_chunks(&[], 256).is_empty());
+    }
+    
+    #[test]
+    fn test_content_defined_chunks_survive_insertion() {
+        let data: Vec<u8> = (0..20_000u32).map(|i| SMCHash::hash(&i.to_le_bytes())[0]).collect();
+        let mut shifted = vec![0xAA];
+        shifted.extend_from_slice(&data);
+        
+        let original: HashSet<[u8; 16]> = content_defined_chunks(&data, 256).into_iter().map(|c| c.1).collect();
+        let shifted_chunks = content_defined_chunks(&shifted, 256);
+        let shared = shifted_chunks.iter().filter(|c| original.contains(&c.1)).count();
+        
+        // Only the first chunk or so should change
+        assert!(shared + 2 >= shifted_chunks.len());
+    }
+    
+    #[test]
+    fn test_block_assemble_from_external_nonce() {
+        // An external miner hashes the serialized header against its target
+        let body = BlockBody::new(sample_transactions(0));
+        let header = body.header(Hash16([3; 16]), 12345, difficulty_to_compact(8));
+        let target = header.target();
+        let nonce = (0..)
+            .find(|&nonce| {
+                let bytes = BlockHeader { nonce, ..header }.to_bytes();
+                meets_target(&SMCHash::hash_personalized(DOMAIN_BLOCK_HEADER, &bytes), target)
+            })
+            .unwrap();
+        
+        let mined = Block::new(Hash16([3; 16]), sample_transactions(0), 12345, 8);
+        assert_eq!(mined.header.nonce, nonce);
+        let assembled = Block::assemble(Hash16([3; 16]), sample_transactions(0), 12345, nonce, 8).unwrap();
+        assert_eq!(assembled, mined);
+        assert!(assembled.validate(8));
+        
+        // The smallest valid nonce was mined, so any smaller one fails
+        let result = Block::assemble(Hash16([3; 16]), sample_transactions(0), 12345, nonce.wrapping_sub(1), 8);
+        assert_eq!(result.unwrap_err(), SmcError::InvalidProofOfWork);
+    }
+    
+    #[test]
+    fn test_parallel_lowest_matches_single_threaded() {
+        let data = "blockchain data".as_bytes();
+        
+        for threads in [1, 2, 4, 7] {
+            assert_eq!(
+                SMCHash::create_proof_of_work_parallel_lowest(data, 8, threads),
+                SMCHash::create_proof_of_work(data, 8),
+                "{} threads", threads
+            );
+        }
+        assert_eq!(
+            SMCHash::create_proof_of_work_parallel_lowest(data, 14, 3),
+            SMCHash::create_proof_of_work(data, 14)
+        );
+    }
+    
+    #[test]
+    fn test_hash_keyed() {
+        let tag = hash_keyed(b"secret key", b"message");
+        assert_ne!(tag, hash_keyed(b"other key", b"message"));
+        assert_ne!(tag, hash_keyed(b"secret key", b"messagf"));
+        assert_ne!(tag, SMCHash::hash(b"secret keymessage"));
+        
+        // The key length is encoded, so key/data boundaries cannot shift
+        assert_ne!(hash_keyed(b"ab", b"c"), hash_keyed(b"a", b"bc"));
+    }
+    
+    #[test]
+    fn test_mac_truncated() {
+        let key = b"packet key";
+        let data = b"short packet";
+        
+        for tag_len in [4, 8, 16] {
+            let tag = mac_truncated(key, data, tag_len);
+            assert_eq!(tag.len(), tag_len);
+            assert_eq!(tag[..], hash_keyed(key, data)[..tag_len]);
+            assert!(verify_mac_truncated(key, data, &tag));
+            
+            let mut flipped = tag.clone();
+            flipped[tag_len - 1] ^= 0x01;
+            assert!(!verify_mac_truncated(key, data, &flipped));
+            assert!(!verify_mac_truncated(b"wrong key", data, &tag));
+        }
+        
+        assert!(!verify_mac_truncated(key, data, &[]));
+        assert!(!verify_mac_truncated(key, data, &[0; 17]));
+    }
+    
+    #[test]
+    fn test_miner_reuses_pool() {
+        let miner = Miner::new(3);
+        assert_eq!(miner.threads(), 3);
+        
+        let mut prev_hash = [0u8; 16];
+        for height in 0u8..3 {
+            let data = [&prev_hash[..], &[height]].concat();
+            let (nonce, hash) = miner.mine(&data, 10);
+            assert!(SMCHash::verify_proof_of_work(&data, nonce, 10, &hash));
+            prev_hash = hash;
+        }
+    }
+    
+    #[test]
+    fn test_block_bytes_version() {
+        let block = Block::new(Hash16([5; 16]), sample_transactions(0), 12345, 4);
+        let mut bytes = block.to_bytes();
+        assert_eq!(bytes[0], 3);
+        assert_eq!(Block::from_bytes(&bytes).unwrap(), block);
+        
+        // Versions 1 and 2 hashed the whole payload rather than a header
+        for old in [1, 2, 4] {
+            bytes[0] = old;
+            assert_eq!(Block::from_bytes(&bytes).unwrap_err(), SmcError::UnsupportedVersion(old.into()));
+        }
+        assert_eq!(Block::from_bytes(&[]).unwrap_err(), SmcError::DecodeTruncated);
+        
+        // The header's own version is checked too
+        bytes[0] = 3;
+        bytes[1] = 9;
+        assert_eq!(Block::from_bytes(&bytes).unwrap_err(), SmcError::UnsupportedVersion(9));
+    }
+    
+    #[cfg(feature = "digest")]
+    #[test]
+    fn test_digest_trait() {
+        use digest::Digest;
+        
+        fn digest_of<D: Digest>(data: &[u8]) -> Vec<u8> {
+            let mut hasher = D::new();
+            hasher.update(&data[..3]);
+            hasher.update(&data[3..]);
+            hasher.finalize().to_vec()
+        }
+        
+        let data = b"generic over RustCrypto hashers";
+        assert_eq!(digest_of::<SMCHash>(data), SMCHash::hash(data));
+        assert_eq!(<SMCHash as Digest>::output_size(), 16);
+        assert_eq!(<SMCHash as Digest>::digest(data)[..], SMCHash::hash(data));
+    }
+    
+    #[test]
+    fn test_io_write() {
+        use std::io::Write;
+        
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let mut hasher = SMCHash::new();
+        let copied = io::copy(&mut std::io::Cursor::new(&data), &mut hasher).unwrap();
+        assert_eq!(copied, data.len() as u64);
+        assert_eq!(hasher.finalize(), SMCHash::hash(&data));
+        
+        let mut hasher = SMCHash::new();
+        write!(hasher, "block {}", 7).unwrap();
+        hasher.flush().unwrap();
+        assert_eq!(hasher.finalize(), SMCHash::hash(b"block 7"));
+    }
+    
+    #[test]
+    fn test_hash_reader() {
+        let data: Vec<u8> = (0..150_000u32).map(|i| (i % 241) as u8).collect();
+        assert_eq!(SMCHash::hash_reader(std::io::Cursor::new(&data)).unwrap(), SMCHash::hash(&data));
+        assert_eq!(SMCHash::hash_reader(std::io::empty()).unwrap(), SMCHash::hash(b""));
+        
+        struct FailingReader;
+        impl Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk error"))
+            }
+        }
+        let failing = std::io::Cursor::new(&data[..10]).chain(FailingReader);
+        assert!(SMCHash::hash_reader(failing).is_err());
+    }
+    
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_hash_file() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("smchash-test-{}.bin", std::process::id()));
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i % 239) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+        let hash = SMCHash::hash_file(&path);
+        
+        std::fs::write(&path, b"").unwrap();
+        let empty = SMCHash::hash_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(hash.unwrap(), SMCHash::hash(&data));
+        assert_eq!(empty.unwrap(), SMCHash::hash(b""));
+        assert!(SMCHash::hash_file(&dir.join("smchash-missing-file")).is_err());
+    }
+    
+    #[test]
+    fn test_finalize_reset() {
+        let mut hasher = SMCHash::new();
+        for message in [&b"first"[..], &[7u8; 100][..], b"", b"last message"] {
+            hasher.update(message);
+            assert_eq!(hasher.finalize_reset(), SMCHash::hash(message));
+        }
+        
+        hasher.update(b"discarded");
+        hasher.reset();
+        hasher.update(b"kept");
+        assert_eq!(hasher.finalize(), SMCHash::hash(b"kept"));
+    }
+    
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_state_checkpoint_serde() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 247) as u8).collect();
+        let (first, rest) = data.split_at(4_321);
+        
+        let checkpoint = {
+            let mut hasher = SMCHash::new();
+            hasher.update(first);
+            serde_json::to_string(&hasher.export_state()).unwrap()
+        };
+        
+        let state: SMCHashState = serde_json::from_str(&checkpoint).unwrap();
+        let mut resumed = SMCHash::from_state(state);
+        resumed.update(rest);
+        assert_eq!(resumed.finalize(), SMCHash::hash(&data));
+    }
+    
+    #[test]
+    fn test_keyed_hash() {
+        let key = [0x42; 16];
+        let mut keyed = SMCHash::new_keyed(&key);
+        keyed.update(b"message");
+        let digest = keyed.finalize_reset();
+        
+        assert_ne!(digest, SMCHash::hash(b"message"));
+        assert_ne!(digest, SMCHash::hash(&[&key[..], &[0; 48], b"message"].concat()));
+        
+        let mut other = SMCHash::new_keyed(&[0x43; 16]);
+        other.update(b"message");
+        assert_ne!(digest, other.finalize());
+        
+        // Resetting keeps the key
+        keyed.update(b"message");
+        assert_eq!(keyed.finalize(), digest);
+    }
+    
+    #[test]
+    fn test_mac() {
+        let key = [7u8; 16];
+        let tag = mac(&key, b"p2p message");
+        assert!(verify_mac(&key, b"p2p message", &tag));
+        assert!(!verify_mac(&key, b"p2p messagf", &tag));
+        assert!(!verify_mac(&[8u8; 16], b"p2p message", &tag));
+        
+        let mut keyed = SMCHash::new_keyed(&key);
+        keyed.update(b"p2p message");
+        assert_ne!(tag, keyed.finalize());
+    }
+    
+    #[test]
+    fn test_finalize_xof() {
+        let mut hasher = SMCHash::new();
+        hasher.update(b"commitment preimage");
+        let mut long = [0u8; 100];
+        hasher.finalize_xof(&mut long);
+        
+        // The hasher was reset, so the same input gives the same stream
+        hasher.update(b"commitment preimage");
+        let mut short = [0u8; 20];
+        hasher.finalize_xof(&mut short);
+        assert_eq!(short[..], long[..20]);
+        
+        // Blocks differ from each other and from the plain digest
+        assert_ne!(long[..16], long[16..32]);
+        assert_ne!(long[..16], SMCHash::hash(b"commitment preimage"));
+        
+        hasher.update(b"other preimage");
+        hasher.finalize_xof(&mut short);
+        assert_ne!(short[..], long[..20]);
+    }
+    
+    #[test]
+    fn test_with_seed() {
+        let seeded = |seed: u64, data: &[u8]| {
+            let mut hasher = SMCHash::with_seed(seed);
+            hasher.update(data);
+            hasher.finalize()
+        };
+        
+        assert_eq!(seeded(1, b"txid"), seeded(1, b"txid"));
+        assert_ne!(seeded(1, b"txid"), seeded(2, b"txid"));
+        assert_ne!(seeded(0, b"txid"), SMCHash::hash(b"txid"));
+        
+        let mut key = [0u8; 16];
+        key[..8].copy_from_slice(&5u64.to_le_bytes());
+        let mut keyed = SMCHash::new_keyed(&key);
+        keyed.update(b"txid");
+        assert_ne!(seeded(5, b"txid"), keyed.finalize());
+        
+        // Per-node orderings of the same items differ
+        let items: Vec<[u8; 1]> = (0..16u8).map(|i| [i]).collect();
+        let order = |seed| {
+            let mut sorted = items.clone();
+            sorted.sort_by_key(|item| seeded(seed, item));
+            sorted
+        };
+        assert_eq!(order(7), order(7));
+        assert_ne!(order(7), order(8));
+    }
+    
+    #[test]
+    fn test_build_hasher() {
+        let mut index: HashMap<[u8; 16], u64, SmcBuildHasher> = HashMap::default();
+        for height in 0..100u64 {
+            index.insert(SMCHash::hash(&height.to_le_bytes()), height);
+        }
+        assert_eq!(index.len(), 100);
+        assert_eq!(index[&SMCHash::hash(&42u64.to_le_bytes())], 42);
+        
+        // A fixed seed hashes reproducibly and matches the seeded SMCHash
+        let fixed = SmcBuildHasher::with_seed(9);
+        assert_eq!(fixed.hash_one(b"key"), SmcBuildHasher::with_seed(9).hash_one(b"key"));
+        assert_ne!(fixed.hash_one(b"key"), SmcBuildHasher::with_seed(10).hash_one(b"key"));
+        
+        let mut hasher = fixed.build_hasher();
+        hasher.write(b"raw bytes");
+        let mut seeded = SMCHash::with_seed(9);
+        seeded.update(b"raw bytes");
+        assert_eq!(hasher.finish().to_le_bytes(), seeded.finalize()[..8]);
+    }
+    
+    #[test]
+    fn test_hash16() {
+        let hash = Hash16(SMCHash::hash(b"hello"));
+        let hex = hash.to_string();
+        assert_eq!(hex, hash_to_hex(&hash));
+        assert_eq!(format!("{:x}", hash), hex);
+        assert_eq!(hex.parse::<Hash16>().unwrap(), hash);
+        assert_eq!(hex.to_uppercase().parse::<Hash16>().unwrap(), hash);
+        assert_eq!(format!("{:?}", Hash16::ZERO), format!("Hash16({})", "0".repeat(32)));
+        
+        assert_eq!("abc".parse::<Hash16>(), Err(SmcError::InvalidHex));
+        assert_eq!("zz".repeat(16).parse::<Hash16>(), Err(SmcError::InvalidHex));
+        assert_eq!(format!("{}é", &hex[..30]).parse::<Hash16>(), Err(SmcError::InvalidHex));
+        
+        let low = Hash16(u128_to_hash(1));
+        let high = Hash16(u128_to_hash(1 << 120));
+        assert!(low < high);
+        assert_eq!(low.cmp(&high), cmp_hashes(&low, &high));
+        assert_eq!(hash.as_ref(), &hash.0[..]);
+        assert_eq!(<[u8; 16]>::from(hash), *hash.as_bytes());
+    }
+    
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_hash16_serde() {
+        let hash = Hash16(SMCHash::hash(b"hello"));
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json, format!("\"{}\"", hash));
+        assert_eq!(serde_json::from_str::<Hash16>(&json).unwrap(), hash);
+        assert!(serde_json::from_str::<Hash16>("\"1234\"").is_err());
+    }
+    
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_block_and_transaction_serde() {
+        let tx = Transaction { from: [1; 16], to: [2; 16], amount: 50, nonce: 3 };
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["from"], "01".repeat(16));
+        assert_eq!(json["to"], "02".repeat(16));
+        assert_eq!(json["amount"], 50);
+        assert_eq!(serde_json::from_value::<Transaction>(json).unwrap(), tx);
+        
+        let block = Block::from_transactions(Hash16([7; 16]), &[tx], 1_700_000_000, 4).unwrap();
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(json["header"]["prev_hash"], "07".repeat(16));
+        assert_eq!(json["header"]["merkle_root"], block.header.merkle_root.to_string());
+        assert_eq!(json["body"]["transactions"][0]["to"], "02".repeat(16));
+        
+        let decoded: Block = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, block);
+        assert!(decoded.validate(4));
+    }
+    
+    #[test]
+    fn test_pow_verifier_find_matches_scalar() {
+        // Header lengths around the one/two padding block boundary
+        for len in [0, 1, 40, 47, 48, 55, 56, 63, 64, 100, 119, 120] {
+            let header: Vec<u8> = (0..len).map(|i| i as u8 ^ 0x5a).collect();
+            let verifier = PowVerifier::new(&header);
+            
+            let expected = (0..).find(|&n| meets_difficulty(&SMCHash::pow_hash(&header, n), 6)).unwrap();
+            assert_eq!(verifier.find(0..1000, 6), Some(expected), "len {}", len);
+            assert_eq!(SMCHash::create_proof_of_work(&header, 6), (expected, SMCHash::pow_hash(&header, expected)));
+            
+            // Ranges that are not a multiple of the lane count
+            assert_eq!(verifier.find(expected..expected + 1, 6), Some(expected));
+            assert_eq!(verifier.find(0..expected, 6), None);
+            assert_eq!(verifier.find(5..5, 0), None);
+        }
+        
+        let verifier = PowVerifier::new(b"end of nonce space");
+        assert_eq!(verifier.find(u64::MAX - 3..u64::MAX, 0), Some(u64::MAX - 3));
+    }
+    
+    #[test]
+    fn test_hash_many() {
+        let lengths = [0, 1, 48, 55, 56, 63, 64, 65, 119, 120, 128, 1000, 48, 48, 3, 200, 0, 77];
+        let messages: Vec<Vec<u8>> = lengths
+            .iter()
+            .enumerate()
+            .map(|(n, &len)| (0..len).map(|i| (i * 31 + n) as u8).collect())
+            .collect();
+        let inputs: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        
+        let expected: Vec<[u8; 16]> = inputs.iter().map(|m| SMCHash::hash(m)).collect();
+        assert_eq!(SMCHash::hash_many(&inputs), expected);
+        assert_eq!(SMCHash::hash_many(&inputs[..3]), expected[..3]);
+        assert!(SMCHash::hash_many(&[]).is_empty());
+    }
+    
+    #[test]
+    fn test_hex_to_hash() {
+        let hash = SMCHash::hash(b"round trip");
+        let hex = hash_to_hex(&hash);
+        assert_eq!(hex_to_hash(&hex), Ok(hash));
+        assert_eq!(hex_to_hash(&hex.to_uppercase()), Ok(hash));
+        assert_eq!(hex_to_hash(&format!("0x{}", hex)), Ok(hash));
+        assert_eq!(hex_to_hash(&format!("0X{}", hex)), Ok(hash));
+        
+        assert_eq!(hex_to_hash("abc"), Err(HexError::OddLength(3)));
+        assert_eq!(hex_to_hash("0xabcd"), Err(HexError::InvalidLength(4)));
+        assert_eq!(hex_to_hash(""), Err(HexError::InvalidLength(0)));
+        assert_eq!(hex_to_hash(&hex[..30]), Err(HexError::InvalidLength(30)));
+        assert_eq!(hex_to_hash("0x12g4"), Err(HexError::InvalidChar { ch: 'g', index: 4 }));
+        assert_eq!(hex_to_hash(&format!("{}é", &hex[..30])), Err(HexError::InvalidChar { ch: 'é', index: 30 }));
+        assert_eq!(hex_to_hash(&format!(" {}", hex)), Err(HexError::InvalidChar { ch: ' ', index: 0 }));
+        
+        assert_eq!(HexError::OddLength(3).to_string(), "odd number of hex digits (3)");
+        assert_eq!(SmcError::from(HexError::InvalidLength(4)), SmcError::InvalidHex);
+    }
+    
+    #[test]
+    fn test_ct_eq() {
+        let hash = SMCHash::hash(b"tag");
+        assert!(ct_eq(&hash, &hash));
+        for i in 0..16 {
+            let mut other = hash;
+            other[i] ^= 0x80;
+            assert!(!ct_eq(&hash, &other));
+            assert!(!Hash16(hash).ct_eq(&Hash16(other)));
+        }
+        assert!(Hash16::ZERO.ct_eq(&Hash16([0; 16])));
+    }
+    
+    #[test]
+    fn test_personalization() {
+        let data = b"same bytes in every domain";
+        let digest = |tag: &[u8]| {
+            let mut hasher = SMCHash::new_with_personalization(tag);
+            hasher.update(data);
+            hasher.finalize()
+        };
+        
+        let domains = [DOMAIN_BLOCK_HEADER, DOMAIN_TRANSACTION, DOMAIN_MERKLE_NODE, DOMAIN_ADDRESS, b""];
+        let mut digests: Vec<[u8; 16]> = domains.iter().map(|tag| digest(tag)).collect();
+        digests.push(SMCHash::hash(data));
+        let distinct: HashSet<_> = digests.iter().collect();
+        assert_eq!(distinct.len(), digests.len());
+        
+        // Deterministic, and reset keeps the personalization
+        assert_eq!(digest(DOMAIN_TRANSACTION), digest(DOMAIN_TRANSACTION));
+        let mut hasher = SMCHash::new_with_personalization(DOMAIN_TRANSACTION);
+        hasher.update(b"discarded");
+        hasher.reset();
+        hasher.update(data);
+        assert_eq!(hasher.finalize(), digest(DOMAIN_TRANSACTION));
+        
+        assert_eq!(SMCHash::hash_personalized(DOMAIN_ADDRESS, data), digest(DOMAIN_ADDRESS));
+        
+        // The tag is not simply prepended to the data
+        assert_ne!(digest(b"ab"), {
+            let mut hasher = SMCHash::new_with_personalization(b"a");
+            hasher.update(b"b");
+            hasher.update(data);
+            hasher.finalize()
+        });
+    }
+    
+    #[test]
+    fn test_hash_salted() {
+        let data = b"tenant upload";
+        let a = hash_salted(&[1; 16], data);
+        let b = hash_salted(&[2; 16], data);
+        assert_ne!(a, b);
+        assert_ne!(a, SMCHash::hash(data));
+        assert_ne!(a, SMCHash::hash(&[&[1u8; 16][..], data].concat()));
+        assert_ne!(a, commit(data, &[1; 16]));
+        
+        let mut hasher = SMCHash::new_salted(&[1; 16]);
+        for chunk in data.chunks(5) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), a);
+        
+        assert!(verify_salted(&[1; 16], data, &a));
+        assert!(!verify_salted(&[2; 16], data, &a));
+        assert!(!verify_salted(&[1; 16], b"tenant upload!", &a));
+    }
+    
+    #[test]
+    fn test_create_proof_of_work_cancellable() {
+        let data = b"cancellable block";
+        let cancel = AtomicBool::new(false);
+        assert_eq!(
+            SMCHash::create_proof_of_work_cancellable(data, 8, &cancel),
+            Some(SMCHash::create_proof_of_work(data, 8))
+        );
+        
+        cancel.store(true, AtomicOrdering::Relaxed);
+        assert_eq!(SMCHash::create_proof_of_work_cancellable(data, 0, &cancel), None);
+        
+        // A search that would never finish stops once another thread cancels it
+        let cancel = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let search = scope.spawn(|| SMCHash::create_proof_of_work_cancellable(data, MAX_DIFFICULTY, &cancel));
+            thread::sleep(Duration::from_millis(20));
+            cancel.store(true, AtomicOrdering::Relaxed);
+            assert_eq!(search.join().unwrap(), None);
+        });
+    }
+    
+    #[test]
+    fn test_create_proof_of_work_bounded() {
+        let data = b"bounded block";
+        let (nonce, hash) = SMCHash::create_proof_of_work(data, 10);
+        
+        assert_eq!(SMCHash::create_proof_of_work_bounded(data, 10, nonce + 1), Some((nonce, hash)));
+        assert_eq!(SMCHash::create_proof_of_work_bounded(data, 10, u64::MAX), Some((nonce, hash)));
+        assert_eq!(SMCHash::create_proof_of_work_bounded(data, 10, nonce), None);
+        assert_eq!(SMCHash::create_proof_of_work_bounded(data, 0, 0), None);
+        assert_eq!(SMCHash::create_proof_of_work_bounded(data, MAX_DIFFICULTY, 10_000), None);
+    }
+    
+    #[test]
+    fn test_create_proof_of_work_parallel() {
+        for threads in [0, 1, 4] {
+            let data = format!("parallel block {}", threads);
+            let (nonce, hash) = SMCHash::create_proof_of_work_parallel(data.as_bytes(), 12, threads);
+            assert!(SMCHash::verify_proof_of_work(data.as_bytes(), nonce, 12, &hash));
+        }
+        
+        // With one worker the search is sequential, so the lowest nonce wins
+        let data = b"single worker";
+        assert_eq!(
+            SMCHash::create_proof_of_work_parallel(data, 10, 1),
+            SMCHash::create_proof_of_work(data, 10)
+        );
+    }
+    
+    #[test]
+    fn test_search_nonce_range() {
+        let data = b"pool work";
+        let expected = SMCHash::create_proof_of_work(data, 10);
+        
+        // Disjoint ranges handed to four workers, merged by the coordinator
+        let ranges = [(0, 300), (300, 700), (700, 1500), (1500, 1_000_000)];
+        let best = ranges
+            .iter()
+            .filter_map(|&(start, end)| SMCHash::search_nonce_range(data, 10, start, end))
+            .min_by_key(|&(nonce, _)| nonce);
+        assert_eq!(best, Some(expected));
+        
+        assert_eq!(SMCHash::search_nonce_range(data, 10, expected.0, expected.0 + 1), Some(expected));
+        assert_eq!(SMCHash::search_nonce_range(data, 10, 0, expected.0), None);
+        assert_eq!(SMCHash::search_nonce_range(data, 0, 42, 42), None);
+        assert_eq!(SMCHash::search_nonce_range(data, 0, 42, 50), Some((42, SMCHash::pow_hash(data, 42))));
+    }
+    
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_create_proof_of_work_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let data = b"async block".to_vec();
+        
+        let found = runtime.block_on(SMCHash::create_proof_of_work_async(data.clone(), 12));
+        assert_eq!(found, SMCHash::create_proof_of_work(&data, 12));
+        
+        // Dropping the future on timeout stops the worker; otherwise dropping
+        // the runtime would wait for it forever
+        let search = SMCHash::create_proof_of_work_async(data, MAX_DIFFICULTY);
+        let timed_out = runtime.block_on(async { tokio::time::timeout(Duration::from_millis(20), search).await });
+        assert!(timed_out.is_err());
+        drop(runtime);
+    }
+    
+    #[test]
+    fn test_midstate() {
+        let header: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let midstate = Midstate::new(&header);
+        assert_eq!(midstate.prefix_len(), 10_000);
+        
+        for nonce in [0, 1, 12345, u64::MAX] {
+            assert_eq!(midstate.pow_hash(nonce), SMCHash::pow_hash(&header, nonce));
+        }
+        assert_eq!(midstate.hash_suffix(b"tail"), SMCHash::hash(&[&header[..], b"tail"].concat()));
+        
+        let mut hasher = midstate.hasher();
+        hasher.update(b"more");
+        assert_eq!(hasher.finalize(), SMCHash::hash(&[&header[..], b"more"].concat()));
+        
+        let verifier = PowVerifier::from_midstate(midstate);
+        assert_eq!(verifier.midstate().prefix_len(), 10_000);
+        assert_eq!(verifier.find(0..1 << 20, 8), SMCHash::search_nonce_range(&header, 8, 0, 1 << 20).map(|(n, _)| n));
+    }
+    
+    #[test]
+    fn test_proof_of_work_target() {
+        let data = b"target block";
+        
+        // Power-of-two targets agree with leading-zero difficulty
+        for difficulty in [0, 1, 8, 12] {
+            let target = u128::MAX >> difficulty;
+            assert_eq!(SMCHash::create_proof_of_work_target(data, target), SMCHash::create_proof_of_work(data, difficulty));
+        }
+        
+        // A target between difficulties 10 and 11
+        let target = (u128::MAX >> 11) + (u128::MAX >> 12);
+        let (nonce, hash) = SMCHash::create_proof_of_work_target(data, target);
+        assert!(hash_to_u128(&hash) <= target);
+        assert!((0..nonce).all(|n| !meets_target(&SMCHash::pow_hash(data, n), target)));
+        
+        assert!(SMCHash::verify_proof_of_work_target(data, nonce, target, &hash));
+        assert!(!SMCHash::verify_proof_of_work_target(data, nonce, hash_to_u128(&hash) - 1, &hash));
+        assert!(!SMCHash::verify_proof_of_work_target(data, nonce + 1, target, &hash));
+        
+        assert!(meets_target(&[0; 16], 0));
+        assert!(meets_target(&[0xff; 16], u128::MAX));
+        assert!(!meets_target(&u128_to_hash(5), 4));
+    }
+    
+    #[test]
+    fn test_difficulty_target_conversions() {
+        assert_eq!(difficulty_to_target(0), [0xff; 16]);
+        assert_eq!(difficulty_to_target(MAX_DIFFICULTY), [0; 16]);
+        assert_eq!(hash_to_u128(&difficulty_to_target(20)), u128::MAX >> 20);
+        
+        for difficulty in 0..=MAX_DIFFICULTY {
+            let target = difficulty_to_target(difficulty);
+            assert_eq!(target_to_difficulty(&target), difficulty);
+            assert_eq!(compact_to_difficulty(difficulty_to_compact(difficulty)), difficulty);
+            
+            // The compact target is never easier than the exact one
+            assert!(hash_to_u128(&compact_to_target(difficulty_to_compact(difficulty))) <= hash_to_u128(&target));
+        }
+        
+        // Hashes meet the target exactly when they meet the difficulty
+        let target = difficulty_to_target(12);
+        for nonce in 0..2000 {
+            let hash = SMCHash::pow_hash(b"migration", nonce);
+            assert_eq!(meets_target(&hash, hash_to_u128(&target)), meets_difficulty(&hash, 12));
+        }
+        
+        // Targets between difficulties give the easier one
+        assert_eq!(target_to_difficulty(&u128_to_hash((u128::MAX >> 11) + (u128::MAX >> 12))), 10);
+    }
+    
+    #[test]
+    fn test_pow_from_start_nonce() {
+        let data = b"shared template";
+        let (lowest, _) = SMCHash::create_proof_of_work(data, 8);
+        assert_eq!(SMCHash::create_proof_of_work_from(data, 8, 0).0, lowest);
+        
+        let (nonce, hash) = SMCHash::create_proof_of_work_from(data, 8, lowest + 1);
+        assert!(nonce > lowest);
+        assert!(SMCHash::verify_proof_of_work(data, nonce, 8, &hash));
+        
+        // Near the top of the nonce space the search wraps around to 0
+        let (nonce, hash) = SMCHash::create_proof_of_work_from(data, 16, u64::MAX - 10);
+        assert!(!(u64::MAX / 2..u64::MAX - 10).contains(&nonce));
+        assert!(SMCHash::verify_proof_of_work(data, nonce, 16, &hash));
+    }
+    
+    #[test]
+    fn test_block_extranonce() {
+        let block = Block::new(Hash16([5; 16]), sample_transactions(0), 12345, 6);
+        
+        // Bumping the coinbase nonce as an extranonce needs re-mining
+        let mut other = block.clone();
+        other.body.transactions[0].nonce = 1;
+        assert!(!other.validate(6));
+        other.mine_from(1 << 40, 6);
+        assert!(other.validate(6));
+        assert!(other.header.nonce >= 1 << 40);
+        assert_ne!(other.header.merkle_root, block.header.merkle_root);
+        
+        let decoded = Block::from_bytes(&other.to_bytes()).unwrap();
+        assert_eq!(decoded.body.transactions[0].nonce, 1);
+        assert!(decoded.validate(6));
+    }
+    
+    #[test]
+    fn test_miner_background_jobs() {
+        let miner = Miner::new(2);
+        let events = miner.subscribe();
+        let timeout = Duration::from_secs(30);
+        
+        // A job too hard to finish, replaced by an easy one
+        miner.start(MiningJob { id: 1, data: b"stale template".to_vec(), difficulty: 100 });
+        miner.start(MiningJob { id: 2, data: b"fresh template".to_vec(), difficulty: 12 });
+        assert_eq!(events.recv_timeout(timeout).unwrap(), MinerEvent::JobReplaced { previous: 1, current: 2 });
+        
+        match events.recv_timeout(timeout).unwrap() {
+            MinerEvent::SolutionFound { job_id, nonce, hash } => {
+                assert_eq!(job_id, 2);
+                assert!(SMCHash::verify_proof_of_work(b"fresh template", nonce, 12, &hash));
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+        
+        // Stopping and blocking mining emit nothing
+        miner.start(MiningJob { id: 3, data: b"next template".to_vec(), difficulty: 100 });
+        miner.stop();
+        let (nonce, hash) = miner.mine(b"blocking", 8);
+        assert!(SMCHash::verify_proof_of_work(b"blocking", nonce, 8, &hash));
+        assert!(events.try_recv().is_err());
+        
+        let stats = miner.stats();
+        assert!(stats.attempts > 0);
+        assert!(stats.hashrate > 0.0);
+        assert!(stats.uptime > Duration::ZERO);
+    }
+    
+    #[test]
+    fn test_verify_proof_of_work_batch() {
+        let headers: Vec<Vec<u8>> = (0..1000u32).map(|i| format!("header {}", i).into_bytes()).collect();
+        let mut items: Vec<(&[u8], u64, u8, [u8; 16])> = headers
+            .iter()
+            .map(|data| {
+                let (nonce, hash) = SMCHash::create_proof_of_work(data, 4);
+                (data.as_slice(), nonce, 4, hash)
+            })
+            .collect();
+        
+        // Break a few: wrong nonce, wrong hash, difficulty not met
+        items[3].1 += 1;
+        items[500].3[0] ^= 1;
+        items[999].2 = 120;
+        
+        let results = SMCHash::verify_proof_of_work_batch(&items);
+        for (item, &result) in items.iter().zip(&results) {
+            assert_eq!(result, SMCHash::verify_proof_of_work(item.0, item.1, item.2, &item.3));
+        }
+        assert_eq!(results.iter().filter(|&&ok| !ok).count(), 3);
+        
+        assert!(SMCHash::verify_proof_of_work_batch(&[]).is_empty());
+        assert_eq!(SMCHash::verify_proof_of_work_batch(&items[..1]), vec![true]);
+    }
+    
+    #[test]
+    fn test_time_to_solution() {
+        let work = PowTarget::Difficulty(20);
+        let hashrate = (1u64 << 20) as f64;
+        assert_eq!(estimate_time_to_solution(work, hashrate), Duration::from_secs(1));
+        assert_eq!(estimate_time_to_solution(work, hashrate / 60.0), Duration::from_secs(60));
+        
+        // The numeric target with the same success probability agrees
+        let target = PowTarget::Target(u128::MAX >> 20);
+        assert_eq!(target.success_probability(), work.success_probability());
+        assert_eq!(estimate_time_to_solution(target, hashrate), Duration::from_secs(1));
+        
+        let median = solution_time_percentile(work, hashrate, 0.5).as_secs_f64();
+        assert!((median - std::f64::consts::LN_2).abs() < 1e-3, "{}", median);
+        let p95 = solution_time_percentile(work, hashrate, 0.95).as_secs_f64();
+        assert!((p95 - 20f64.ln()).abs() < 1e-3, "{}", p95);
+        assert_eq!(solution_time_percentile(work, hashrate, 0.0), Duration::ZERO);
+        assert_eq!(solution_time_percentile(work, hashrate, 1.0), Duration::MAX);
+        
+        // Difficulty 0 and the maximum target succeed on the first attempt
+        assert_eq!(PowTarget::Difficulty(0).success_probability(), 1.0);
+        assert_eq!(PowTarget::Target(u128::MAX).success_probability(), 1.0);
+        assert_eq!(estimate_time_to_solution(PowTarget::Difficulty(200), hashrate), Duration::MAX);
+        assert_eq!(estimate_time_to_solution(PowTarget::Difficulty(128), 1.0), Duration::MAX);
+    }
+    
+    #[test]
+    fn test_create_proof_of_work_with() {
+        let data = b"research header";
+        let verifier = PowVerifier::new(data);
+        
+        // Trailing zero byte, and a vanity first byte
+        let (nonce, hash) = SMCHash::create_proof_of_work_with(data, |hash| hash[15] == 0);
+        assert_eq!(hash[15], 0);
+        assert_eq!(hash, verifier.hash(nonce));
+        assert!((0..nonce).all(|n| verifier.hash(n)[15] != 0));
+        
+        let (nonce, hash) = SMCHash::create_proof_of_work_with(data, |hash| hash[0] == 0xab);
+        assert_eq!(hash[0], 0xab);
+        assert_eq!(verifier.find_with(0..nonce + 1, |hash| hash[0] == 0xab), Some(nonce));
+        
+        // A difficulty predicate matches the standard search
+        let standard = SMCHash::create_proof_of_work(data, 10);
+        assert_eq!(SMCHash::create_proof_of_work_with(data, |hash| leading_zero_bits(hash) >= 10), standard);
+    }
+    
+    #[test]
+    fn test_fractional_difficulty() {
+        // Whole bits give exactly the integer difficulty targets
+        for difficulty in 0..=MAX_DIFFICULTY {
+            let fractional = FractionalDifficulty::from_bits(difficulty);
+            assert_eq!(u128_to_hash(fractional.to_target()), difficulty_to_target(difficulty));
+        }
+        
+        // Targets shrink with every step, by about 0.27%
+        let mut previous = u128::MAX;
+        for steps in 1..=FractionalDifficulty::MAX.steps() {
+            let target = FractionalDifficulty::from_steps(steps).to_target();
+            assert!(target <= previous, "step {}", steps);
+            previous = target;
+        }
+        let half = FractionalDifficulty::from_steps(20 * 256 + 128);
+        assert_eq!(half.bits(), 20.5);
+        let ratio = half.to_target() as f64 / (u128::MAX >> 20) as f64;
+        assert!((ratio / std::f64::consts::FRAC_1_SQRT_2 - 1.0).abs() < 1.3e-4, "{}", ratio);
+        
+        let data = b"fractional block";
+        let (nonce, hash) = SMCHash::create_proof_of_work_fractional(data, half);
+        assert!(SMCHash::verify_proof_of_work_fractional(data, nonce, half, &hash));
+        assert!(meets_target(&hash, half.to_target()));
+        assert!(!SMCHash::verify_proof_of_work_fractional(data, nonce, FractionalDifficulty::MAX, &hash));
+    }
+    
+    #[test]
+    fn test_txid_is_domain_separated() {
+        let tx = Transaction::new([1; 16], [2; 16], 100, 0);
+        assert_eq!(tx.txid().0, SMCHash::hash_personalized(DOMAIN_TRANSACTION, &tx.serialize()));
+        assert_ne!(tx.txid().0, SMCHash::hash(&tx.serialize()));
+    }
+    
+    #[test]
+    fn test_transaction_validate() {
+        assert_eq!(Transaction::new([1; 16], [2; 16], 100, 0).validate(), Ok(()));
+        assert_eq!(Transaction::new([0; 16], [2; 16], 5000, 0).validate(), Ok(()));
+        assert_eq!(Transaction::new([1; 16], [2; 16], 0, 0).validate(), Err(TransactionError::ZeroAmount));
+        assert_eq!(Transaction::new([1; 16], [1; 16], 100, 0).validate(), Err(TransactionError::SelfTransfer));
+        assert_eq!(Transaction::new([1; 16], [0; 16], 100, 0).validate(), Err(TransactionError::ZeroRecipient));
+        
+        // Blocks refuse invalid transactions before mining
+        let bad = Transaction::new([1; 16], [1; 16], 100, 0);
+        let err = Block::from_transactions(Hash16::ZERO, std::slice::from_ref(&bad), 12345, 4).unwrap_err();
+        assert_eq!(err, SmcError::InvalidTransaction(bad.txid(), TransactionError::SelfTransfer));
+        assert!(err.to_string().ends_with(": sender and recipient are the same"));
+    }
+    
+    #[test]
+    fn test_block_body_validate() {
+        let tx = Transaction::new([1; 16], [2; 16], 100, 0);
+        let bad = Transaction::new([1; 16], [0; 16], 100, 0);
+        assert_eq!(BlockBody::new(sample_transactions(1)).validate(), Ok(()));
+        assert_eq!(
+            BlockBody::new(vec![tx.clone(), tx.clone()]).validate(),
+            Err(SmcError::DuplicateTransaction(tx.txid()))
+        );
+        let invalid = SmcError::InvalidTransaction(bad.txid(), TransactionError::ZeroRecipient);
+        assert_eq!(BlockBody::new(vec![tx.clone(), bad.clone()]).validate(), Err(invalid.clone()));
+        
+        // Blocks built without checks are caught wherever they are validated
+        let block = Block::new(Hash16::ZERO, vec![tx.clone(), tx.clone()], 12345, 4);
+        assert!(block.body.matches(&block.header) && block.header.validate());
+        assert!(!block.validate(4));
+        let duplicate = SmcError::DuplicateTransaction(tx.txid());
+        assert_eq!(verify_chain_links(std::slice::from_ref(&block), 4), Err((0, duplicate.clone())));
+        assert_eq!(Block::decode(&block.encode()), Err(duplicate));
+        assert_eq!(Block::assemble(Hash16::ZERO, vec![bad], 12345, 0, 4), Err(invalid));
+    }
+    
+    #[test]
+    fn test_block_encode_decode() {
+        let block = Block::new(Hash16([5; 16]), sample_transactions(77), 12345, 4);
+        let encoded = block.encode();
+        assert_eq!(&encoded[..4], b"SMCB");
+        assert_eq!(&encoded[4..], block.to_bytes().as_slice());
+        assert_eq!(Block::decode(&encoded).unwrap(), block);
+        assert!(Block::decode(&encoded).unwrap().validate(4));
+        
+        assert_eq!(Block::decode(&block.to_bytes()).unwrap_err(), SmcError::InvalidMagic);
+        assert_eq!(Block::decode(b"SM").unwrap_err(), SmcError::InvalidMagic);
+        assert_eq!(Block::decode(b"SMCB").unwrap_err(), SmcError::DecodeTruncated);
+        assert_eq!(Block::decode(&encoded[..encoded.len() - 1]).unwrap_err(), SmcError::DecodeTruncated);
+        
+        let mut newer = encoded.clone();
+        newer[4] = 4;
+        assert_eq!(Block::decode(&newer).unwrap_err(), SmcError::UnsupportedVersion(4));
+    }
+    
+    #[test]
+    fn test_block_header() {
+        let body = BlockBody::new(vec![
+            Transaction::new([0; 16], [2; 16], 5000, 0),
+            Transaction::new([1; 16], [2; 16], 100, 0),
+            Transaction::new([1; 16], [3; 16], 200, 1),
+        ]);
+        let mut header = body.header(Hash16([7; 16]), 1_700_000_000, difficulty_to_compact(8));
+        assert_eq!(header.version, BlockHeader::VERSION);
+        assert!(body.matches(&header));
+        
+        let hash = header.mine();
+        assert_eq!(hash, header.hash());
+        assert!(header.validate());
+        assert!(leading_zero_bits(&hash) >= 8);
+        
+        // The smallest nonce was found, by the same hasher as `hash`
+        assert!((0..header.nonce).all(|nonce| !BlockHeader { nonce, ..header }.validate()));
+        
+        // Header hashes are domain separated from plain hashes of the bytes
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), BlockHeader::SERIALIZED_LEN);
+        assert_eq!(hash.0, SMCHash::hash_personalized(DOMAIN_BLOCK_HEADER, &bytes));
+        assert_ne!(hash.0, SMCHash::hash(&bytes));
+        assert_eq!(BlockHeader::from_bytes(&bytes), Ok(header));
+        
+        // Mining from a later nonce finds a later solution
+        let mut later = header;
+        let later_hash = later.mine_from(header.nonce + 1);
+        assert!(later.nonce > header.nonce);
+        assert_eq!(later_hash, later.hash());
+        assert!(later.validate());
+        
+        let mut unknown = bytes;
+        unknown[0] = 2;
+        assert_eq!(BlockHeader::from_bytes(&unknown), Err(SmcError::UnsupportedVersion(2)));
+        
+        let mut tampered = header;
+        tampered.timestamp += 1;
+        assert_ne!(tampered.hash(), hash);
+        
+        // Any change to the transactions breaks the commitment
+        let mut other = body.clone();
+        other.transactions[1].amount += 1;
+        assert!(!other.matches(&header));
+        other.transactions[1].amount -= 1;
+        other.transactions.swap(1, 2);
+        assert!(!other.matches(&header));
+    }
+    
+    #[test]
+    fn test_blockchain() {
+        let blocks = build_chain(4, 4);
+        let mut chain = Blockchain::new(blocks[0].clone(), 4);
+        assert_eq!((chain.height(), chain.tip()), (0, &blocks[0]));
+        for block in &blocks[1..] {
+            chain.append(block.clone()).unwrap();
+        }
+        assert_eq!((chain.height(), chain.tip()), (3, &blocks[3]));
+        assert_eq!(chain.get(1), Some(&blocks[1]));
+        assert_eq!(chain.get(4), None);
+        assert_eq!(chain.blocks(), blocks.as_slice());
+        assert_eq!(chain.validate_all(), Ok(()));
+        
+        // Rejected blocks leave the chain as it was
+        let tip = chain.tip().hash();
+        let stale = Block::new(blocks[2].hash(), sample_transactions(9), 2000, 4);
+        assert_eq!(chain.append(stale), Err(ChainError::PrevHashMismatch));
+        let early = Block::new(tip, sample_transactions(9), 1002, 4);
+        assert_eq!(chain.append(early), Err(ChainError::TimestampTooEarly));
+        let mut forged = Block::new(tip, sample_transactions(9), 2000, 4);
+        forged.body.transactions[1].amount *= 10;
+        assert_eq!(chain.append(forged), Err(ChainError::InvalidProofOfWork));
+        let weak = (0..).map(|i| Block::new(tip, sample_transactions(i), 2000, 0)).find(|b| !b.validate(4)).unwrap();
+        assert_eq!(chain.append(weak), Err(ChainError::InvalidProofOfWork));
+        let tx = Transaction::new([1; 16], [2; 16], 100, 0);
+        let duplicate = Block::new(tip, vec![tx.clone(), tx.clone()], 2000, 4);
+        let err = chain.append(duplicate).unwrap_err();
+        assert_eq!(err, ChainError::InvalidBody(SmcError::DuplicateTransaction(tx.txid())));
+        assert!(err.to_string().starts_with("invalid block body: duplicate transaction"));
+        assert_eq!(chain.height(), 3);
+        
+        // Equal timestamps are allowed
+        chain.append(Block::new(tip, sample_transactions(9), 1003, 4)).unwrap();
+        assert_eq!(chain.height(), 4);
+        
+        // validate_all also covers the trusted genesis block
+        let mut bad_genesis = blocks[0].clone();
+        bad_genesis.body.transactions.pop();
+        assert_eq!(Blockchain::new(bad_genesis, 4).validate_all(), Err((0, ChainError::InvalidProofOfWork)));
+    }
+}
+
+/// A block: the `BlockHeader` carrying the proof of work and the
+/// `BlockBody` of transactions it commits to by merkle root. The miner's
+/// address and any extranonce go in the coinbase transaction, which the
+/// merkle root covers.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Block {
+    pub header: BlockHeader,
+    pub body: BlockBody,
+}
+
+#[cfg(feature = "alloc")]
+impl Block {
+    /// Format version written as the first byte of `to_bytes`
+    pub const SERIALIZATION_VERSION: u8 = 3;
+    
+    /// Prefix of `encode`, identifying an smc block on the wire
+    pub const MAGIC: [u8; 4] = *b"SMCB";
+    
+    /// Creates and mines a block of `transactions` on top of `prev_hash`.
+    /// The transactions are taken as given, and a block with invalid ones
+    /// fails `validate`; see `from_transactions`.
+    ///
+    /// Panics if `difficulty` exceeds `MAX_DIFFICULTY`.
+    pub fn new(prev_hash: Hash16, transactions: Vec<Transaction>, timestamp: u64, difficulty: u8) -> Self {
+        let body = BlockBody::new(transactions);
+        let mut block = Block { header: body.header(prev_hash, timestamp, 0), body };
+        block.mine_from(0, difficulty);
+        block
+    }
+    
+    /// Mines the block as it stands: points the header at the current
+    /// transactions and at `difficulty`, then tries nonces upwards from
+    /// `start_nonce` as in `SMCHash::create_proof_of_work_from`. Only the
+    /// header is hashed, so the cost per nonce does not depend on the number
+    /// of transactions.
+    ///
+    /// Panics if `difficulty` exceeds `MAX_DIFFICULTY`.
+    pub fn mine_from(&mut self, start_nonce: u64, difficulty: u8) {
+        assert_difficulty_in_range(difficulty);
+        self.header.merkle_root = self.body.merkle_root();
+        self.header.bits = difficulty_to_compact(difficulty);
+        self.header.mine_from(start_nonce);
+    }
+    
+    /// Builds a block from a nonce mined elsewhere (e.g. by an external
+    /// mining service) without searching again. The transactions must pass
+    /// `BlockBody::validate`, and the header hash at that nonce must meet
+    /// the header's `target()`, i.e.
+    /// `compact_to_target(difficulty_to_compact(difficulty))`. That target
+    /// is rounded down from `difficulty_to_target(difficulty)`, so external
+    /// miners must search against it: a nonce that only meets `difficulty`
+    /// can still be rejected.
+    ///
+    /// Panics if `difficulty` exceeds `MAX_DIFFICULTY`.
+    pub fn assemble(
+        prev_hash: Hash16,
+        transactions: Vec<Transaction>,
+        timestamp: u64,
+        nonce: u64,
+        difficulty: u8,
+    ) -> Result<Self, SmcError> {
+        assert_difficulty_in_range(difficulty);
+        let body = BlockBody::new(transactions);
+        body.validate()?;
+        let mut header = body.header(prev_hash, timestamp, difficulty_to_compact(difficulty));
+        header.nonce = nonce;
+        
+        let block = Block { header, body };
+        if !block.validate(difficulty) {
+            return Err(SmcError::InvalidProofOfWork);
+        }
+        Ok(block)
+    }
+    
+    /// Hash of the header, which identifies the block
+    pub fn hash(&self) -> Hash16 {
+        self.header.hash()
+    }
+    
+    /// Difficulty actually achieved by the block (the leading zero bits of its
+    /// hash), or 0 if the header does not commit to the transactions
+    pub fn achieved_difficulty(&self) -> u32 {
+        if !self.body.matches(&self.header) {
+            return 0;
+        }
+        leading_zero_bits(&self.hash())
+    }
+    
+    /// Checks that the transactions pass `BlockBody::validate`, that the
+    /// header commits to them and that its hash meets both its own `bits`
+    /// and `difficulty`
+    pub fn validate(&self, difficulty: u8) -> bool {
+        let hash = self.hash();
+        self.body.validate().is_ok()
+            && self.body.matches(&self.header)
+            && self.header.validate()
+            && meets_difficulty(&hash, difficulty)
+    }
+    
+    /// Serializes the block: the `SERIALIZATION_VERSION` byte, the header as
+    /// in `BlockHeader::to_bytes`, the transaction count as a little-endian
+    /// u32 and each transaction as in `Transaction::serialize`
+    ///
+    /// Panics if there are more than `u32::MAX` transactions.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let transactions = &self.body.transactions;
+        let count = u32::try_from(transactions.len()).expect("too many transactions to serialize");
+        let mut bytes = Vec::with_capacity(1 + BlockHeader::SERIALIZED_LEN + 4 + transactions.len() * Transaction::SERIALIZED_LEN);
+        bytes.push(Self::SERIALIZATION_VERSION);
+        bytes.extend_from_slice(&self.header.to_bytes());
+        bytes.extend_from_slice(&count.to_le_bytes());
+        for tx in transactions {
+            bytes.extend_from_slice(&tx.serialize());
+        }
+        bytes
+    }
+    
+    /// Decodes a block serialized with `to_bytes`. The input must contain
+    /// exactly one block: leftover bytes are rejected so that the same block
+    /// has a single valid encoding. Versions other than
+    /// `SERIALIZATION_VERSION`, of the block or of its header, are rejected
+    /// with `UnsupportedVersion`, and invalid or duplicate transactions as in
+    /// `BlockBody::validate`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SmcError> {
+        let mut reader = ByteReader::new(bytes);
+        let version = reader.read_u8()?;
+        if version != Self::SERIALIZATION_VERSION {
+            return Err(SmcError::UnsupportedVersion(version.into()));
+        }
+        let header = BlockHeader::from_bytes(reader.take(BlockHeader::SERIALIZED_LEN)?.try_into().unwrap())?;
+        let count = reader.read_u32()?;
+        
+        // The count is untrusted, so the vector grows with the input instead
+        let mut transactions = Vec::new();
+        for _ in 0..count {
+            transactions.push(Transaction {
+                from: reader.read_hash()?,
+                to: reader.read_hash()?,
+                amount: reader.read_u64()?,
+                nonce: reader.read_u64()?,
+            });
+        }
+        reader.finish()?;
+        
+        let body = BlockBody::new(transactions);
+        body.validate()?;
+        Ok(Block { header, body })
+    }
+    
+    /// Wire encoding for exchanging blocks between nodes: `MAGIC` followed
+    /// by the versioned `to_bytes` layout. Equal blocks always encode to the
+    /// same bytes.
+    ///
+    /// Panics if there are more than `u32::MAX` transactions.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Self::MAGIC.to_vec();
+        bytes.extend_from_slice(&self.to_bytes());
+        bytes
+    }
+    
+    /// Decodes a block produced by `encode`, rejecting input without `MAGIC`
+    /// with `InvalidMagic` and otherwise failing as `from_bytes` does. The
+    /// proof of work is not checked; see `validate`.
+    pub fn decode(bytes: &[u8]) -> Result<Self, SmcError> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.take(Self::MAGIC.len()).map_err(|_| SmcError::InvalidMagic)? != Self::MAGIC {
+            return Err(SmcError::InvalidMagic);
+        }
+        Self::from_bytes(reader.bytes)
+    }
+    
+    /// Builds and mines a block from a list of transactions, failing as
+    /// `BlockBody::validate` does instead of mining an invalid block
+    pub fn from_transactions(
+        prev_hash: Hash16,
+        transactions: &[Transaction],
+        timestamp: u64,
+        difficulty: u8,
+    ) -> Result<Self, SmcError> {
+        let body = BlockBody::new(transactions.to_vec());
+        body.validate()?;
+        Ok(Block::new(prev_hash, body.transactions, timestamp, difficulty))
+    }
+}
+
+/// Fixed-size block header: the only part of a block that is mined and
+/// hashed. The transactions are committed to through `merkle_root` (see
+/// `BlockBody`), so a proof-of-work attempt costs the same however large
+/// the block is, and headers can be checked without the transactions.
+///
+/// The hash is `to_bytes()` hashed under `DOMAIN_BLOCK_HEADER`. The nonce
+/// is serialized last, so mining resumes from a midstate of the other
+/// fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockHeader {
+    pub version: u32,
+    pub prev_hash: Hash16,
+    pub merkle_root: Hash16,
+    pub timestamp: u64,
+    /// Target the hash must meet, in compact form (see `compact_to_target`)
+    pub bits: u32,
+    pub nonce: u64,
+}
+
+impl BlockHeader {
+    /// Version of headers created by `new`, the only one `from_bytes` accepts
+    pub const VERSION: u32 = 1;
+    
+    /// Length of `to_bytes`
+    pub const SERIALIZED_LEN: usize = 56;
+    
+    // Everything before the nonce
+    const PREFIX_LEN: usize = Self::SERIALIZED_LEN - 8;
+    
+    /// Unmined header (nonce 0) at the current `VERSION`
+    pub fn new(prev_hash: Hash16, merkle_root: Hash16, timestamp: u64, bits: u32) -> Self {
+        BlockHeader {
+            version: Self::VERSION,
+            prev_hash,
+            merkle_root,
+            timestamp,
+            bits,
+            nonce: 0,
+        }
+    }
+    
+    /// Serializes the header: version, prev_hash, merkle_root, timestamp,
+    /// bits and nonce, integers little-endian
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_LEN] {
+        let mut bytes = [0u8; Self::SERIALIZED_LEN];
+        bytes[..4].copy_from_slice(&self.version.to_le_bytes());
+        bytes[4..20].copy_from_slice(&self.prev_hash.0);
+        bytes[20..36].copy_from_slice(&self.merkle_root.0);
+        bytes[36..44].copy_from_slice(&self.timestamp.to_le_bytes());
+        bytes[44..48].copy_from_slice(&self.bits.to_le_bytes());
+        bytes[48..].copy_from_slice(&self.nonce.to_le_bytes());
+        bytes
+    }
+    
+    /// Decodes a header serialized with `to_bytes`, rejecting versions other
+    /// than `VERSION` with `UnsupportedVersion`
+    pub fn from_bytes(bytes: &[u8; Self::SERIALIZED_LEN]) -> Result<Self, SmcError> {
+        let version = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+        if version != Self::VERSION {
+            return Err(SmcError::UnsupportedVersion(version));
+        }
+        Ok(BlockHeader {
+            version,
+            prev_hash: Hash16(bytes[4..20].try_into().unwrap()),
+            merkle_root: Hash16(bytes[20..36].try_into().unwrap()),
+            timestamp: u64::from_le_bytes(bytes[36..44].try_into().unwrap()),
+            bits: u32::from_le_bytes(bytes[44..48].try_into().unwrap()),
+            nonce: u64::from_le_bytes(bytes[48..].try_into().unwrap()),
+        })
+    }
+    
+    pub fn hash(&self) -> Hash16 {
+        Hash16(SMCHash::hash_personalized(DOMAIN_BLOCK_HEADER, &self.to_bytes()))
+    }
+    
+    /// Numeric target decoded from `bits`
+    pub fn target(&self) -> u128 {
+        u128::from_be_bytes(compact_to_target(self.bits))
+    }
+    
+    /// Sets `nonce` to the smallest one whose hash meets `target()` and
+    /// returns that hash
+    pub fn mine(&mut self) -> Hash16 {
+        self.mine_from(0)
+    }
+    
+    /// Same as `mine`, trying nonces upwards from `start_nonce` and wrapping
+    /// around to 0 after `u64::MAX`
+    ///
+    /// Panics if no nonce meets the target.
+    pub fn mine_from(&mut self, start_nonce: u64) -> Hash16 {
+        let verifier = self.verifier();
+        let target = self.target();
+        let nonce = find_wrapping(&verifier, start_nonce, |hash| meets_target(hash, target))
+            .unwrap_or_else(|| panic!("no nonce meets target {:#034x}", target));
+        self.nonce = nonce;
+        Hash16(verifier.hash(nonce))
+    }
+    
+    /// Whether the header's hash meets its own `bits`. Whether `bits` is
+    /// the right target for the chain is up to the caller.
+    pub fn validate(&self) -> bool {
+        meets_target(&self.hash(), self.target())
+    }
+    
+    // Verifier resuming from the fields before the nonce, in the header domain
+    fn verifier(&self) -> PowVerifier {
+        let mut hasher = SMCHash::new_with_personalization(DOMAIN_BLOCK_HEADER);
+        hasher.update(&self.to_bytes()[..Self::PREFIX_LEN]);
+        PowVerifier::from_midstate(Midstate { hasher })
+    }
+}
+
+/// Transactions of a block, committed to by its header's `merkle_root`
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockBody {
+    pub transactions: Vec<Transaction>,
+}
+
+#[cfg(feature = "alloc")]
+impl BlockBody {
+    pub fn new(transactions: Vec<Transaction>) -> Self {
+        BlockBody { transactions }
+    }
+    
+    /// Merkle root of the txids, in order (see `merkle_root`)
+    pub fn merkle_root(&self) -> Hash16 {
+        let txids: Vec<[u8; 16]> = self.transactions.iter().map(|tx| tx.txid().0).collect();
+        Hash16(merkle_root(&txids))
+    }
+    
+    /// Unmined header committing to this body
+    pub fn header(&self, prev_hash: Hash16, timestamp: u64, bits: u32) -> BlockHeader {
+        BlockHeader::new(prev_hash, self.merkle_root(), timestamp, bits)
+    }
+    
+    /// Whether `header` commits to exactly these transactions
+    pub fn matches(&self, header: &BlockHeader) -> bool {
+        self.merkle_root() == header.merkle_root
+    }
+    
+    /// Checks that every transaction is valid (see `Transaction::validate`)
+    /// and that none appears more than once (by txid)
+    pub fn validate(&self) -> Result<(), SmcError> {
+        let mut seen = BTreeSet::new();
+        for tx in &self.transactions {
+            let txid = tx.txid();
+            tx.validate().map_err(|reason| SmcError::InvalidTransaction(txid, reason))?;
+            if !seen.insert(txid) {
+                return Err(SmcError::DuplicateTransaction(txid));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Mines a header for `body` over `prev_hash` starting at
+/// `start_timestamp`, bumping the timestamp and restarting from nonce 0
+/// whenever the whole nonce space is exhausted. Returns the `(nonce,
+/// timestamp, hash)` of the mined header; the hash commits to both, so
+/// `body.header(prev_hash, timestamp, difficulty_to_compact(difficulty))`
+/// with that nonce reproduces it.
+///
+/// Panics if `difficulty` exceeds `MAX_DIFFICULTY`.
+#[cfg(feature = "alloc")]
+pub fn mine_block_rolling(
+    prev_hash: Hash16,
+    body: &BlockBody,
+    difficulty: u8,
+    start_timestamp: u64,
+) -> (u64, u64, Hash16) {
+    let header = mine_rolling_up_to(prev_hash, body, difficulty, start_timestamp, u64::MAX);
+    (header.nonce, header.timestamp, header.hash())
+}
+
+// `mine_block_rolling` with a configurable last nonce, so rolling is testable
+#[cfg(feature = "alloc")]
+fn mine_rolling_up_to(
+    prev_hash: Hash16,
+    body: &BlockBody,
+    difficulty: u8,
+    start_timestamp: u64,
+    max_nonce: u64,
+) -> BlockHeader {
+    assert_difficulty_in_range(difficulty);
+    let mut header = body.header(prev_hash, start_timestamp, difficulty_to_compact(difficulty));
+    let target = header.target();
+    
+    loop {
+        let verifier = header.verifier();
+        let found = verifier
+            .find_target(0..max_nonce, target)
+            .or_else(|| meets_target(&verifier.hash(max_nonce), target).then_some(max_nonce));
+        if let Some(nonce) = found {
+            header.nonce = nonce;
+            return header;
+        }
+        
+        header.timestamp = header.timestamp.checked_add(1).expect("timestamp space exhausted");
+    }
+}
+
+/// Validates a sequence of blocks: every block must have a valid body (see
+/// `BlockBody::validate`), carry valid proof of work at `difficulty` and
+/// link to the hash of the block before it. On failure,
+/// returns the index of the first offending block and the reason.
+#[cfg(feature = "alloc")]
+pub fn verify_chain_links(blocks: &[Block], difficulty: u8) -> Result<(), (usize, SmcError)> {
+    for (i, block) in blocks.iter().enumerate() {
+        block.body.validate().map_err(|err| (i, err))?;
+        if !block.validate(difficulty) {
+            return Err((i, SmcError::InvalidProofOfWork));
+        }
+        
+        if i > 0 && !block.header.prev_hash.ct_eq(&blocks[i - 1].hash()) {
+            return Err((i, SmcError::PrevHashMismatch));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Index of the last block on which two chains (both starting from genesis)
+/// agree, comparing block hashes. Returns `None` if they share no blocks.
+#[cfg(feature = "alloc")]
+pub fn common_ancestor(a: &[Block], b: &[Block]) -> Option<usize> {
+    a.iter()
+        .zip(b)
+        .take_while(|(x, y)| x.hash().ct_eq(&y.hash()))
+        .count()
+        .checked_sub(1)
+}
+
+/// Whether two chains hold identical blocks at every index up to and
+/// including `n`
+#[cfg(feature = "alloc")]
+pub fn chains_agree_up_to(a: &[Block], b: &[Block], n: usize) -> bool {
+    common_ancestor(a, b).is_some_and(|ancestor| ancestor >= n)
+}
+
+/// Chain of blocks from a genesis block, mined at a fixed difficulty. Blocks
+/// are only added through `append`, which keeps the chain valid: each block
+/// must pass `Block::validate` at the difficulty, link to the tip by
+/// `prev_hash` and not be timestamped before it, or is rejected with the
+/// matching `ChainError`.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blockchain {
+    blocks: Vec<Block>,
+    difficulty: u8,
+}
+
+#[cfg(feature = "alloc")]
+impl Blockchain {
+    /// Starts a chain at `genesis`, which is trusted as given (a genesis
+    /// block is usually hard-coded); `validate_all` checks it too.
+    ///
+    /// Panics if `difficulty` exceeds `MAX_DIFFICULTY`.
+    pub fn new(genesis: Block, difficulty: u8) -> Self {
+        assert_difficulty_in_range(difficulty);
+        Blockchain { blocks: vec![genesis], difficulty }
+    }
+    
+    /// Adds `block` on top of the tip, leaving the chain unchanged if the
+    /// block is invalid there
+    pub fn append(&mut self, block: Block) -> Result<(), ChainError> {
+        check_successor(self.tip(), &block, self.difficulty)?;
+        self.blocks.push(block);
+        Ok(())
+    }
+    
+    /// Height of the tip; the genesis block is at height 0
+    pub fn height(&self) -> u64 {
+        self.blocks.len() as u64 - 1
+    }
+    
+    /// Most recent block
+    pub fn tip(&self) -> &Block {
+        self.blocks.last().expect("a chain always holds its genesis block")
+    }
+    
+    /// Block at `height`, if the chain is that long
+    pub fn get(&self, height: u64) -> Option<&Block> {
+        self.blocks.get(usize::try_from(height).ok()?)
+    }
+    
+    /// All blocks, genesis first
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+    
+    pub fn difficulty(&self) -> u8 {
+        self.difficulty
+    }
+    
+    /// Re-checks the whole chain, genesis included: transactions and proof
+    /// of work for every block, then links and timestamps. On failure,
+    /// returns the height of the first offending block and the reason.
+    pub fn validate_all(&self) -> Result<(), (u64, ChainError)> {
+        check_block(&self.blocks[0], self.difficulty).map_err(|e| (0, e))?;
+        for (height, pair) in (1u64..).zip(self.blocks.windows(2)) {
+            check_successor(&pair[0], &pair[1], self.difficulty).map_err(|e| (height, e))?;
+        }
+        Ok(())
+    }
+}
+
+// Rules for `block` on its own, wherever it sits in the chain
+#[cfg(feature = "alloc")]
+fn check_block(block: &Block, difficulty: u8) -> Result<(), ChainError> {
+    block.body.validate().map_err(ChainError::InvalidBody)?;
+    if !block.validate(difficulty) {
+        return Err(ChainError::InvalidProofOfWork);
+    }
+    Ok(())
+}
+
+// Rules for `block` directly following `parent`
+#[cfg(feature = "alloc")]
+fn check_successor(parent: &Block, block: &Block, difficulty: u8) -> Result<(), ChainError> {
+    check_block(block, difficulty)?;
+    if !block.header.prev_hash.ct_eq(&parent.hash()) {
+        return Err(ChainError::PrevHashMismatch);
+    }
+    if block.header.timestamp < parent.header.timestamp {
+        return Err(ChainError::TimestampTooEarly);
+    }
+    Ok(())
+}
+
+/// Errors returned by block, chain and decoding APIs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmcError {
+    /// The block hash does not satisfy the required difficulty, or does not
+    /// match the hash of the block contents
+    InvalidProofOfWork,
+    /// A block's `prev_hash` does not match the hash of the block before it
+    PrevHashMismatch,
+    /// The same transaction appears more than once in the block
+    DuplicateTransaction(Hash16),
+    /// The input ended before a complete value could be decoded
+    DecodeTruncated,
+    /// Bytes were left over after a complete value was decoded
+    TrailingBytes,
+    /// A string was not valid hex of the expected length
+    InvalidHex,
+    /// A string contained a character outside the Base58 alphabet
+    InvalidBase58,
+    /// An encoded value's checksum did not match its payload
+    ChecksumMismatch,
+    /// An encoded value uses a format version this build cannot read
+    UnsupportedVersion(u32),
+    /// The input does not start with the expected magic bytes
+    InvalidMagic,
+    /// A transaction in the block breaks a `Transaction::validate` rule
+    InvalidTransaction(Hash16, TransactionError),
+}
+
+impl fmt::Display for SmcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmcError::InvalidProofOfWork => write!(f, "invalid proof of work"),
+            SmcError::PrevHashMismatch => write!(f, "previous hash does not match"),
+            SmcError::DuplicateTransaction(txid) => {
+                write!(f, "duplicate transaction {} in block", txid)
+            }
+            SmcError::DecodeTruncated => write!(f, "input truncated"),
+            SmcError::TrailingBytes => write!(f, "trailing bytes after encoded value"),
+            SmcError::InvalidHex => write!(f, "invalid hex string"),
+            SmcError::InvalidBase58 => write!(f, "invalid base58 string"),
+            SmcError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            SmcError::UnsupportedVersion(version) => write!(f, "unsupported format version {}", version),
+            SmcError::InvalidMagic => write!(f, "invalid magic bytes"),
+            SmcError::InvalidTransaction(txid, reason) => write!(f, "invalid transaction {}: {}", txid, reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SmcError {}
+
+/// Why a string could not be parsed by `hex_to_hash`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexError {
+    /// A character that is not a hex digit, at byte offset `index`
+    InvalidChar { ch: char, index: usize },
+    /// An odd number of hex digits, which cannot form whole bytes
+    OddLength(usize),
+    /// A whole number of bytes, but not the 32 digits of a hash
+    InvalidLength(usize),
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexError::InvalidChar { ch, index } => write!(f, "invalid hex character {:?} at offset {}", ch, index),
+            HexError::OddLength(len) => write!(f, "odd number of hex digits ({})", len),
+            HexError::InvalidLength(len) => write!(f, "expected 32 hex digits, got {}", len),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HexError {}
+
+impl From<HexError> for SmcError {
+    fn from(_: HexError) -> Self {
+        SmcError::InvalidHex
+    }
+}
+
+/// Rule broken by a transaction, from `Transaction::validate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionError {
+    /// Moves no value
+    ZeroAmount,
+    /// Sender and recipient are the same address
+    SelfTransfer,
+    /// Sent to the zero address, which only coinbase transactions use
+    ZeroRecipient,
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionError::ZeroAmount => write!(f, "amount is zero"),
+            TransactionError::SelfTransfer => write!(f, "sender and recipient are the same"),
+            TransactionError::ZeroRecipient => write!(f, "recipient is the zero address"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TransactionError {}
+
+/// Why `Blockchain` rejected a block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+    /// The block's transactions break a `BlockBody::validate` rule
+    InvalidBody(SmcError),
+    /// The block fails `Block::validate` at the chain's difficulty
+    InvalidProofOfWork,
+    /// The block's `prev_hash` is not the hash of the block before it
+    PrevHashMismatch,
+    /// The block is timestamped before the block before it
+    TimestampTooEarly,
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::InvalidBody(err) => write!(f, "invalid block body: {}", err),
+            ChainError::InvalidProofOfWork => write!(f, "invalid proof of work"),
+            ChainError::PrevHashMismatch => write!(f, "previous hash does not match"),
+            ChainError::TimestampTooEarly => write!(f, "timestamp is earlier than the previous block"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ChainError {}
+
+// Cursor over an encoded value that reports truncated and trailing input
+#[cfg(feature = "alloc")]
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes }
+    }
+    
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SmcError> {
+        if self.bytes.len() < len {
+            return Err(SmcError::DecodeTruncated);
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+    
+    fn read_u8(&mut self) -> Result<u8, SmcError> {
+        Ok(self.take(1)?[0])
+    }
+    
+    fn read_hash(&mut self) -> Result<[u8; 16], SmcError> {
+        Ok(self.take(16)?.try_into().unwrap())
+    }
+    
+    fn read_u32(&mut self) -> Result<u32, SmcError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    
+    fn read_u64(&mut self) -> Result<u64, SmcError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    
+    // Succeeds only if the whole input was consumed
+    fn finish(self) -> Result<(), SmcError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(SmcError::TrailingBytes)
+        }
+    }
+}
+
+/// Value transfer between two addresses, the unit blocks are built from
+/// (see `Block::from_transactions`). `serialize` is the canonical encoding
+/// and `txid` its hash.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transaction {
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+    pub from: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+    pub to: [u8; 16],
+    pub amount: u64,
+    pub nonce: u64,
+}
+
+impl Transaction {
+    /// Length of the serialized form: two addresses plus amount and nonce
+    pub const SERIALIZED_LEN: usize = 48;
+    
+    pub fn new(from: [u8; 16], to: [u8; 16], amount: u64, nonce: u64) -> Self {
+        Self { from, to, amount, nonce }
+    }
+    
+    /// Serializes the transaction with little-endian integers
+    #[cfg(feature = "alloc")]
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut builder = HashBuilder::new();
+        builder
+            .push_hash(&self.from)
+            .push_hash(&self.to)
+            .push_u64(self.amount)
+            .push_u64(self.nonce);
+        builder.into_bytes()
+    }
+    
+    /// Transaction id: the serialized transaction hashed under
+    /// `DOMAIN_TRANSACTION`, so it never equals the plain hash of block data
+    #[cfg(feature = "alloc")]
+    pub fn txid(&self) -> Hash16 {
+        Hash16(SMCHash::hash_personalized(DOMAIN_TRANSACTION, &self.serialize()))
+    }
+    
+    /// Coinbase transactions mint new coins and are sent from the zero address
+    pub fn is_coinbase(&self) -> bool {
+        self.from == [0; 16]
+    }
+    
+    /// Checks the rules every transaction in a block must follow: a non-zero
+    /// amount, distinct sender and recipient, and a recipient other than the
+    /// zero address. Balances and nonce ordering need chain state and are
+    /// not checked here.
+    pub fn validate(&self) -> Result<(), TransactionError> {
+        if self.amount == 0 {
+            return Err(TransactionError::ZeroAmount);
+        }
+        if self.from == self.to {
+            return Err(TransactionError::SelfTransfer);
+        }
+        if self.to == [0; 16] {
+            return Err(TransactionError::ZeroRecipient);
+        }
+        Ok(())
+    }
+}
+
+/// Picks transactions from a mempool for the next block, highest `amount`
+/// first as a stand-in for fee priority (ties keep pool order)
+///
+/// `max_count` is the block's total transaction capacity. One slot is
+/// reserved for the miner's coinbase, so at most `max_count - 1`
+/// transactions are returned. Coinbase transactions in the pool and repeated
+/// txids are skipped.
+#[cfg(feature = "alloc")]
+pub fn select_transactions(pool: &[Transaction], max_count: usize) -> Vec<Transaction> {
+    let mut candidates: Vec<&Transaction> = pool.iter().filter(|tx| !tx.is_coinbase()).collect();
+    candidates.sort_by_key(|tx| core::cmp::Reverse(tx.amount));
+    
+    let mut seen = BTreeSet::new();
+    candidates
+        .into_iter()
+        .filter(|tx| seen.insert(tx.txid()))
+        .take(max_count.saturating_sub(1))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,7 @@
+//! Alternative proof-of-work functions
+//!
+//! The standard proof of work (`SMCHash::create_proof_of_work`) is a single
+//! hash per attempt. The modes here trade speed for other properties and
+//! come with their own miner and verifier.
+
+pub mod memory_hard;
@@ -0,0 +1,204 @@
+//! Memory-hard proof of work: every attempt fills a scratchpad with SMCHash
+//! and then reads it back at data-dependent positions, so hashing speed is
+//! bound by memory rather than by the compression function alone
+//!
+//! Per attempt, with `n` 16-byte cells:
+//!
+//! 1. `seed` is the personalized hash (`DOMAIN`) of the parameters, the
+//!    data and the little-endian nonce
+//! 2. fill: `cell[0] = H(seed || 0)`, `cell[i] = H(cell[i-1] || i)`
+//! 3. mix, `reads` times: `j = acc mod n`, `acc = H(acc || cell[j])`,
+//!    `cell[j] ^= acc`, starting from `acc = seed`, where `acc mod n` reads
+//!    the first 8 bytes of `acc` as a little-endian integer
+//!
+//! Every `H` in steps 2 and 3 is the plain hash of exactly 32 bytes: the
+//! 16-byte first operand, then the second operand, then zero padding. The
+//! index `i` is 8 bytes little-endian followed by 8 zero bytes; a cell fills
+//! all 16 bytes.
+//!
+//! The final `acc` is the hash, which meets a difficulty as usual. The
+//! parameters are part of the seed, so a proof only verifies under the
+//! parameters it was mined with, and verifying costs one full attempt.
+
+use crate::{assert_difficulty_in_range, ct_eq, meets_difficulty, SMCHash};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+/// Personalization of the seed hash
+pub const DOMAIN: &[u8] = b"smc/memory-hard";
+
+/// Scratchpad size and number of random reads, fixed for a chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Params {
+    memory_kib: u32,
+    reads: u32,
+}
+
+impl Params {
+    /// Smallest scratchpad, in KiB
+    pub const MIN_MEMORY_KIB: u32 = 16;
+    
+    /// Largest scratchpad, in KiB (1 GiB)
+    pub const MAX_MEMORY_KIB: u32 = 1 << 20;
+    
+    /// 2 MiB scratchpad read 2^16 times: a few milliseconds per attempt
+    pub const DEFAULT: Params = Params { memory_kib: 2048, reads: 1 << 16 };
+    
+    /// Parameters for a `memory_kib` KiB scratchpad read `reads` times.
+    /// More reads make it costlier to recompute cells than to store them.
+    ///
+    /// Panics if `memory_kib` is not a power of two within
+    /// `MIN_MEMORY_KIB..=MAX_MEMORY_KIB`, or `reads` is zero.
+    pub fn new(memory_kib: u32, reads: u32) -> Self {
+        assert!(
+            memory_kib.is_power_of_two() && (Self::MIN_MEMORY_KIB..=Self::MAX_MEMORY_KIB).contains(&memory_kib),
+            "scratchpad size must be a power of two between {} and {} KiB, got {}",
+            Self::MIN_MEMORY_KIB,
+            Self::MAX_MEMORY_KIB,
+            memory_kib
+        );
+        assert!(reads > 0, "memory-hard proof of work needs at least one read");
+        Params { memory_kib, reads }
+    }
+    
+    pub fn memory_kib(&self) -> u32 {
+        self.memory_kib
+    }
+    
+    pub fn reads(&self) -> u32 {
+        self.reads
+    }
+    
+    fn cells(&self) -> usize {
+        self.memory_kib as usize * 1024 / 16
+    }
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params::DEFAULT
+    }
+}
+
+// Scratchpad reused across attempts, so mining allocates only once
+struct Scratchpad {
+    params: Params,
+    cells: Vec<[u8; 16]>,
+}
+
+impl Scratchpad {
+    fn new(params: Params) -> Self {
+        Scratchpad { params, cells: vec![[0; 16]; params.cells()] }
+    }
+    
+    fn hash(&mut self, data: &[u8], nonce: u64) -> [u8; 16] {
+        let mut hasher = SMCHash::new_with_personalization(DOMAIN);
+        hasher.update(&self.params.memory_kib.to_le_bytes());
+        hasher.update(&self.params.reads.to_le_bytes());
+        hasher.update(data);
+        hasher.update(&nonce.to_le_bytes());
+        let seed = hasher.finalize();
+        
+        let mut previous = seed;
+        for (i, cell) in (0u64..).zip(self.cells.iter_mut()) {
+            *cell = SMCHash::hash_fixed(&concat(&previous, &i.to_le_bytes()));
+            previous = *cell;
+        }
+        
+        // The cell count is a power of two, so masking picks the index
+        let mask = self.cells.len() as u64 - 1;
+        let mut acc = seed;
+        for _ in 0..self.params.reads {
+            let index = (u64::from_le_bytes(acc[..8].try_into().unwrap()) & mask) as usize;
+            acc = SMCHash::hash_fixed(&concat(&acc, &self.cells[index]));
+            for (byte, mixed) in self.cells[index].iter_mut().zip(acc) {
+                *byte ^= mixed;
+            }
+        }
+        acc
+    }
+}
+
+fn concat<const N: usize>(a: &[u8; 16], b: &[u8; N]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out[..16].copy_from_slice(a);
+    out[16..16 + N].copy_from_slice(b);
+    out
+}
+
+/// Memory-hard hash of `data` and `nonce` under `params`
+pub fn hash(data: &[u8], nonce: u64, params: &Params) -> [u8; 16] {
+    Scratchpad::new(*params).hash(data, nonce)
+}
+
+/// Finds the smallest nonce whose memory-hard hash meets `difficulty`,
+/// returning it with the hash
+///
+/// Panics if `difficulty` exceeds `MAX_DIFFICULTY`.
+pub fn create_proof_of_work(data: &[u8], difficulty: u8, params: &Params) -> (u64, [u8; 16]) {
+    assert_difficulty_in_range(difficulty);
+    let mut scratchpad = Scratchpad::new(*params);
+    
+    for nonce in 0..=u64::MAX {
+        let hash = scratchpad.hash(data, nonce);
+        if meets_difficulty(&hash, difficulty) {
+            return (nonce, hash);
+        }
+    }
+    panic!("no nonce meets difficulty {}", difficulty);
+}
+
+/// Checks that `expected_hash` is the memory-hard hash of `data` and `nonce`
+/// under `params` and meets `difficulty`. Costs one full attempt, scratchpad
+/// included.
+pub fn verify_proof_of_work(data: &[u8], nonce: u64, difficulty: u8, params: &Params, expected_hash: &[u8; 16]) -> bool {
+    let hash = hash(data, nonce, params);
+    ct_eq(&hash, expected_hash) && meets_difficulty(&hash, difficulty)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    
+    fn small() -> Params {
+        Params::new(16, 256)
+    }
+    
+    #[test]
+    fn test_memory_hard_hash() {
+        let params = small();
+        let digest = hash(b"header", 7, &params);
+        assert_eq!(digest, hash(b"header", 7, &params));
+        assert_ne!(digest, hash(b"header", 8, &params));
+        assert_ne!(digest, hash(b"headeR", 7, &params));
+        assert_ne!(digest, SMCHash::pow_hash(b"header", 7));
+        
+        // Every parameter is committed to
+        assert_ne!(digest, hash(b"header", 7, &Params::new(32, 256)));
+        assert_ne!(digest, hash(b"header", 7, &Params::new(16, 257)));
+        
+        // Reusing a scratchpad gives the same hashes as a fresh one
+        let mut scratchpad = Scratchpad::new(params);
+        for nonce in 0..3 {
+            assert_eq!(scratchpad.hash(b"header", nonce), hash(b"header", nonce, &params));
+        }
+    }
+    
+    #[test]
+    fn test_memory_hard_proof_of_work() {
+        let params = small();
+        let (nonce, digest) = create_proof_of_work(b"block", 6, &params);
+        assert!(verify_proof_of_work(b"block", nonce, 6, &params, &digest));
+        assert!((0..nonce).all(|n| !meets_difficulty(&hash(b"block", n, &params), 6)));
+        
+        assert!(!verify_proof_of_work(b"block", nonce, 6, &Params::new(32, 256), &digest));
+        assert!(!verify_proof_of_work(b"block", nonce + 1, 6, &params, &digest));
+        assert!(!verify_proof_of_work(b"other", nonce, 6, &params, &digest));
+    }
+    
+    #[test]
+    #[should_panic(expected = "power of two")]
+    fn test_params_reject_odd_sizes() {
+        Params::new(48, 256);
+    }
+}
@@ -0,0 +1,226 @@
+//! Python bindings, building an importable `smchash` module
+//!
+//! Build the extension with
+//! `cargo rustc --release --features python,pyo3/extension-module --crate-type cdylib`
+//! and put `target/release/libsmchash.so` on the Python path as
+//! `smchash.so` (`smchash.pyd` on Windows). Hashes are `bytes` of
+//! length 16; functions taking a hash raise `ValueError` for any other
+//! length. Mining releases the GIL, so other Python threads keep running.
+//!
+//! ```python
+//! import smchash
+//! h = smchash.Hasher()
+//! for chunk in chunks:
+//!     h.update(chunk)
+//! print(h.hexdigest())
+//! ```
+
+use crate::{hash_to_hex, Block, Hash16, SMCHash, Transaction};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+#[pymodule(name = "smchash")]
+fn smchash_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(hash, m)?)?;
+    m.add_function(wrap_pyfunction!(verify, m)?)?;
+    m.add_function(wrap_pyfunction!(create_proof_of_work, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_proof_of_work, m)?)?;
+    m.add_class::<PyHasher>()?;
+    m.add_class::<PyBlock>()?;
+    Ok(())
+}
+
+/// hash(data: bytes) -> bytes
+#[pyfunction]
+fn hash<'py>(py: Python<'py>, data: &[u8]) -> Bound<'py, PyBytes> {
+    PyBytes::new(py, &SMCHash::hash(data))
+}
+
+/// verify(data: bytes, expected: bytes) -> bool, comparing in constant time
+#[pyfunction]
+fn verify(data: &[u8], expected: &[u8]) -> PyResult<bool> {
+    Ok(SMCHash::verify(data, &to_hash(expected)?))
+}
+
+/// create_proof_of_work(data: bytes, difficulty: int) -> (nonce, hash)
+#[pyfunction]
+fn create_proof_of_work<'py>(py: Python<'py>, data: &[u8], difficulty: u8) -> (u64, Bound<'py, PyBytes>) {
+    let (nonce, hash) = py.detach(|| SMCHash::create_proof_of_work(data, difficulty));
+    (nonce, PyBytes::new(py, &hash))
+}
+
+/// verify_proof_of_work(data: bytes, nonce: int, difficulty: int, expected_hash: bytes) -> bool
+#[pyfunction]
+fn verify_proof_of_work(data: &[u8], nonce: u64, difficulty: u8, expected_hash: &[u8]) -> PyResult<bool> {
+    Ok(SMCHash::verify_proof_of_work(data, nonce, difficulty, &to_hash(expected_hash)?))
+}
+
+/// Streaming hasher with a hashlib-style interface
+#[pyclass(name = "Hasher")]
+#[derive(Default)]
+struct PyHasher {
+    inner: SMCHash,
+}
+
+#[pymethods]
+impl PyHasher {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+    
+    fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+    
+    /// Digest of everything fed so far; more data may still be added
+    fn digest<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.inner.clone().finalize())
+    }
+    
+    fn hexdigest(&self) -> String {
+        hash_to_hex(&self.inner.clone().finalize())
+    }
+    
+    fn copy(&self) -> Self {
+        PyHasher { inner: self.inner.clone() }
+    }
+}
+
+/// A mined block, see `smchash::Block`
+#[pyclass(name = "Block")]
+struct PyBlock {
+    inner: Block,
+}
+
+#[pymethods]
+impl PyBlock {
+    /// Block(prev_hash: bytes, transactions: list[(from, to, amount, nonce)],
+    /// timestamp: int, difficulty: int), mining it at `difficulty`
+    #[new]
+    fn new(
+        py: Python<'_>,
+        prev_hash: &[u8],
+        transactions: Vec<(Vec<u8>, Vec<u8>, u64, u64)>,
+        timestamp: u64,
+        difficulty: u8,
+    ) -> PyResult<Self> {
+        let prev_hash = Hash16(to_hash(prev_hash)?);
+        let transactions = transactions
+            .into_iter()
+            .map(|(from, to, amount, nonce)| Ok(Transaction::new(to_hash(&from)?, to_hash(&to)?, amount, nonce)))
+            .collect::<PyResult<Vec<_>>>()?;
+        let inner = py.detach(|| Block::new(prev_hash, transactions, timestamp, difficulty));
+        Ok(PyBlock { inner })
+    }
+    
+    /// Decodes the output of `to_bytes`
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        let inner = Block::from_bytes(bytes).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyBlock { inner })
+    }
+    
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.inner.to_bytes())
+    }
+    
+    fn validate(&self, difficulty: u8) -> bool {
+        self.inner.validate(difficulty)
+    }
+    
+    #[getter]
+    fn prev_hash<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.inner.header.prev_hash.as_bytes())
+    }
+    
+    #[getter]
+    fn merkle_root<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.inner.header.merkle_root.as_bytes())
+    }
+    
+    #[getter]
+    fn timestamp(&self) -> u64 {
+        self.inner.header.timestamp
+    }
+    
+    #[getter]
+    fn bits(&self) -> u32 {
+        self.inner.header.bits
+    }
+    
+    #[getter]
+    fn nonce(&self) -> u64 {
+        self.inner.header.nonce
+    }
+    
+    /// Transactions as (from, to, amount, nonce) tuples
+    #[getter]
+    fn transactions<'py>(&self, py: Python<'py>) -> Vec<(Bound<'py, PyBytes>, Bound<'py, PyBytes>, u64, u64)> {
+        self.inner
+            .body
+            .transactions
+            .iter()
+            .map(|tx| (PyBytes::new(py, &tx.from), PyBytes::new(py, &tx.to), tx.amount, tx.nonce))
+            .collect()
+    }
+    
+    #[getter]
+    fn hash<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.inner.hash().as_bytes())
+    }
+    
+    fn __repr__(&self) -> String {
+        let header = &self.inner.header;
+        format!("Block(hash={}, nonce={}, timestamp={})", self.inner.hash(), header.nonce, header.timestamp)
+    }
+}
+
+fn to_hash(bytes: &[u8]) -> PyResult<[u8; 16]> {
+    bytes
+        .try_into()
+        .map_err(|_| PyValueError::new_err(format!("expected a 16-byte hash, got {} bytes", bytes.len())))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_python_module() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "smchash").unwrap();
+            smchash_module(&module).unwrap();
+            
+            let hash = module.getattr("hash").unwrap().call1((&b"abc"[..],)).unwrap();
+            assert_eq!(hash.extract::<Vec<u8>>().unwrap(), SMCHash::hash(b"abc"));
+            
+            let hasher = module.getattr("Hasher").unwrap().call0().unwrap();
+            hasher.call_method1("update", (&b"ab"[..],)).unwrap();
+            hasher.call_method1("update", (&b"c"[..],)).unwrap();
+            assert_eq!(hasher.call_method0("digest").unwrap().extract::<Vec<u8>>().unwrap(), SMCHash::hash(b"abc"));
+            assert_eq!(hasher.call_method0("hexdigest").unwrap().extract::<String>().unwrap(), hash_to_hex(&SMCHash::hash(b"abc")));
+            
+            let verify = module.getattr("verify").unwrap();
+            assert!(verify.call1((&b"abc"[..], hash)).unwrap().extract::<bool>().unwrap());
+            assert!(verify.call1((&b"abc"[..], &b"short"[..])).unwrap_err().is_instance_of::<PyValueError>(py));
+            
+            let transactions = vec![(vec![1u8; 16], vec![2u8; 16], 100u64, 0u64)];
+            let block = module.getattr("Block").unwrap().call1((&[0u8; 16][..], transactions.clone(), 12345, 4)).unwrap();
+            assert!(block.call_method1("validate", (4,)).unwrap().extract::<bool>().unwrap());
+            let got: Vec<(Vec<u8>, Vec<u8>, u64, u64)> = block.getattr("transactions").unwrap().extract().unwrap();
+            assert_eq!(got, transactions);
+            let short = vec![(vec![1u8; 15], vec![2u8; 16], 100u64, 0u64)];
+            let err = module.getattr("Block").unwrap().call1((&[0u8; 16][..], short, 12345, 4)).unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+            let bytes = block.call_method0("to_bytes").unwrap();
+            let decoded = module.getattr("Block").unwrap().call_method1("from_bytes", (bytes,)).unwrap();
+            assert_eq!(
+                decoded.getattr("hash").unwrap().extract::<Vec<u8>>().unwrap(),
+                block.getattr("hash").unwrap().extract::<Vec<u8>>().unwrap()
+            );
+        });
+    }
+}
@@ -0,0 +1,402 @@
+//! Stratum-style mining server, so existing mining farm software can point at
+//! an smc node
+//!
+//! Clients speak newline-delimited JSON-RPC over TCP:
+//!
+//! - `mining.subscribe []` gives `[subscription_id, extranonce]`, the
+//!   extranonce (16 hex digits) being unique to the connection, followed by
+//!   a `mining.set_difficulty` and a `mining.notify`
+//! - `mining.authorize [worker, password]` is accepted for any worker
+//! - `mining.submit [worker, job_id, nonce]` checks a share, the nonce as 16
+//!   hex digits, and gives `true` or an error `[code, message, null]`
+//!
+//! The server pushes `mining.notify [job_id, data, network_difficulty,
+//! clean_jobs]` with the hex of the data to mine (a nonce solves it as in
+//! `SMCHash::verify_proof_of_work`) and `mining.set_difficulty
+//! [difficulty]`, difficulties being leading zero bits. Work comes from a
+//! `JobSource`, asked for a job per connection so each can carry that
+//! connection's extranonce (e.g. as the nonce of the coinbase transaction).
+
+use crate::{assert_difficulty_in_range, meets_difficulty, PowVerifier};
+use serde_json::{json, Value};
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Jobs a client may still submit shares for; older ones are stale
+const RECENT_JOBS: usize = 4;
+
+// Stratum error codes
+const ERR_OTHER: i64 = 20;
+const ERR_JOB_NOT_FOUND: i64 = 21;
+const ERR_DUPLICATE_SHARE: i64 = 22;
+const ERR_LOW_DIFFICULTY: i64 = 23;
+const ERR_NOT_SUBSCRIBED: i64 = 25;
+
+/// Work handed to a connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    /// Identifies the job in `mining.submit`; jobs built from the same
+    /// template should share it
+    pub id: String,
+    /// Data the nonce is appended to
+    pub data: Vec<u8>,
+    /// Difficulty at which a share also solves the block
+    pub difficulty: u8,
+}
+
+/// A share meeting the job's network difficulty, i.e. a solved block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    pub job_id: String,
+    /// Extranonce of the connection that found it
+    pub extranonce: u64,
+    pub worker: String,
+    pub nonce: u64,
+    pub hash: [u8; 16],
+}
+
+/// Supplies work to a `Server` and receives solved blocks
+pub trait JobSource: Send + Sync {
+    /// Current job for the connection with `extranonce`
+    fn job(&self, extranonce: u64) -> Job;
+    
+    /// Called with every share that solves its block
+    fn submit_block(&self, share: Share);
+}
+
+/// Stratum server. `run` serves connections on the calling thread; the
+/// remaining methods may be called from other threads meanwhile, e.g.
+/// `notify` whenever the node's block template changes.
+pub struct Server {
+    listener: TcpListener,
+    source: Box<dyn JobSource>,
+    share_difficulty: AtomicU8,
+    next_extranonce: AtomicU64,
+    clients: Mutex<Vec<Arc<Client>>>,
+}
+
+struct Client {
+    extranonce: u64,
+    writer: Mutex<TcpStream>,
+    // Recent jobs, newest last, with the nonces already accepted for each
+    jobs: Mutex<VecDeque<(Job, HashSet<u64>)>>,
+}
+
+impl Server {
+    /// Listens on `addr`, asking for shares of `share_difficulty`
+    ///
+    /// Panics if `share_difficulty` exceeds `MAX_DIFFICULTY`.
+    pub fn bind(addr: impl ToSocketAddrs, source: impl JobSource + 'static, share_difficulty: u8) -> io::Result<Self> {
+        assert_difficulty_in_range(share_difficulty);
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            source: Box::new(source),
+            share_difficulty: AtomicU8::new(share_difficulty),
+            next_extranonce: AtomicU64::new(0),
+            clients: Mutex::new(Vec::new()),
+        })
+    }
+    
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+    
+    /// Accepts connections forever, serving each on its own thread. Returns
+    /// only if accepting fails.
+    pub fn run(self: &Arc<Self>) -> io::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            let server = Arc::clone(self);
+            thread::spawn(move || server.serve(stream));
+        }
+    }
+    
+    /// Sends every subscribed connection a fresh job from the source.
+    /// `clean_jobs` tells miners to drop their current work at once, as
+    /// after a new block; earlier jobs stay valid for shares either way.
+    pub fn notify(&self, clean_jobs: bool) {
+        for client in self.clients() {
+            self.send_job(&client, clean_jobs);
+        }
+    }
+    
+    /// Changes the share difficulty and announces it to every subscribed
+    /// connection
+    ///
+    /// Panics if `difficulty` exceeds `MAX_DIFFICULTY`.
+    pub fn set_difficulty(&self, difficulty: u8) {
+        assert_difficulty_in_range(difficulty);
+        self.share_difficulty.store(difficulty, Ordering::Relaxed);
+        for client in self.clients() {
+            client.send(&json!({"id": null, "method": "mining.set_difficulty", "params": [difficulty]}));
+        }
+    }
+    
+    fn clients(&self) -> Vec<Arc<Client>> {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    
+    fn serve(&self, stream: TcpStream) {
+        let Ok(writer) = stream.try_clone() else { return };
+        let client = Arc::new(Client {
+            extranonce: self.next_extranonce.fetch_add(1, Ordering::Relaxed),
+            writer: Mutex::new(writer),
+            jobs: Mutex::new(VecDeque::new()),
+        });
+        
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let request = serde_json::from_str::<Value>(&line);
+            let reply = match &request {
+                Ok(request) => self.handle(&client, request),
+                Err(_) => error_reply(&Value::Null, ERR_OTHER, "malformed request"),
+            };
+            client.send(&reply);
+            
+            let subscribing = request.is_ok_and(|request| method(&request) == Some("mining.subscribe"));
+            if subscribing && self.subscribe(&client) {
+                client.send(&json!({
+                    "id": null,
+                    "method": "mining.set_difficulty",
+                    "params": [self.share_difficulty.load(Ordering::Relaxed)],
+                }));
+                self.send_job(&client, true);
+            }
+        }
+        
+        self.clients.lock().unwrap_or_else(|e| e.into_inner()).retain(|c| !Arc::ptr_eq(c, &client));
+    }
+    
+    fn handle(&self, client: &Client, request: &Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let params = request.get("params").and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+        
+        match method(request) {
+            Some("mining.subscribe") => {
+                let extranonce = format!("{:016x}", client.extranonce);
+                json!({"id": id, "result": [extranonce, extranonce], "error": null})
+            }
+            Some("mining.authorize") => json!({"id": id, "result": true, "error": null}),
+            Some("mining.submit") => match self.submit(client, params) {
+                Ok(()) => json!({"id": id, "result": true, "error": null}),
+                Err((code, message)) => error_reply(&id, code, message),
+            },
+            _ => error_reply(&id, ERR_OTHER, "unknown method"),
+        }
+    }
+    
+    // Registers the client for notifications; false if it already was
+    fn subscribe(&self, client: &Arc<Client>) -> bool {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        if clients.iter().any(|c| Arc::ptr_eq(c, client)) {
+            return false;
+        }
+        clients.push(Arc::clone(client));
+        true
+    }
+    
+    fn submit(&self, client: &Client, params: &[Value]) -> Result<(), (i64, &'static str)> {
+        let [worker, job_id, nonce] = params else {
+            return Err((ERR_OTHER, "expected [worker, job_id, nonce]"));
+        };
+        let (Some(worker), Some(job_id), Some(nonce)) = (worker.as_str(), job_id.as_str(), nonce.as_str()) else {
+            return Err((ERR_OTHER, "expected [worker, job_id, nonce]"));
+        };
+        let nonce = u64::from_str_radix(nonce, 16).map_err(|_| (ERR_OTHER, "invalid nonce"))?;
+        
+        let mut jobs = client.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if jobs.is_empty() {
+            return Err((ERR_NOT_SUBSCRIBED, "not subscribed"));
+        }
+        let (job, accepted) = jobs
+            .iter_mut()
+            .find(|(job, _)| job.id == job_id)
+            .ok_or((ERR_JOB_NOT_FOUND, "job not found"))?;
+        if accepted.contains(&nonce) {
+            return Err((ERR_DUPLICATE_SHARE, "duplicate share"));
+        }
+        
+        // A share solving the block always counts, even below share difficulty
+        let share_difficulty = self.share_difficulty.load(Ordering::Relaxed).min(job.difficulty);
+        let hash = PowVerifier::new(&job.data).hash(nonce);
+        if !meets_difficulty(&hash, share_difficulty) {
+            return Err((ERR_LOW_DIFFICULTY, "low difficulty share"));
+        }
+        accepted.insert(nonce);
+        
+        if meets_difficulty(&hash, job.difficulty) {
+            let share = Share {
+                job_id: job.id.clone(),
+                extranonce: client.extranonce,
+                worker: worker.to_string(),
+                nonce,
+                hash,
+            };
+            drop(jobs);
+            self.source.submit_block(share);
+        }
+        Ok(())
+    }
+    
+    fn send_job(&self, client: &Client, clean_jobs: bool) {
+        let job = self.source.job(client.extranonce);
+        let notify = json!({
+            "id": null,
+            "method": "mining.notify",
+            "params": [job.id, to_hex(&job.data), job.difficulty, clean_jobs],
+        });
+        
+        let mut jobs = client.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if jobs.len() == RECENT_JOBS {
+            jobs.pop_front();
+        }
+        jobs.push_back((job, HashSet::new()));
+        drop(jobs);
+        client.send(&notify);
+    }
+}
+
+impl Client {
+    // Write errors are ignored: the reader sees the connection close
+    fn send(&self, message: &Value) {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(writer, "{}", message);
+    }
+}
+
+fn method(request: &Value) -> Option<&str> {
+    request.get("method").and_then(Value::as_str)
+}
+
+fn error_reply(id: &Value, code: i64, message: &str) -> Value {
+    json!({"id": id, "result": null, "error": [code, message, null]})
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::SMCHash;
+    use std::io::Lines;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    
+    const NETWORK_DIFFICULTY: u8 = 10;
+    
+    struct TestSource {
+        template: Arc<Mutex<&'static [u8]>>,
+        blocks: Mutex<mpsc::Sender<Share>>,
+    }
+    
+    impl JobSource for TestSource {
+        fn job(&self, extranonce: u64) -> Job {
+            let template = *self.template.lock().unwrap();
+            let mut data = template.to_vec();
+            data.extend_from_slice(&extranonce.to_le_bytes());
+            Job { id: to_hex(template), data, difficulty: NETWORK_DIFFICULTY }
+        }
+        
+        fn submit_block(&self, share: Share) {
+            self.blocks.lock().unwrap().send(share).unwrap();
+        }
+    }
+    
+    struct TestClient {
+        writer: TcpStream,
+        lines: Lines<BufReader<TcpStream>>,
+    }
+    
+    impl TestClient {
+        fn connect(addr: SocketAddr) -> Self {
+            let stream = TcpStream::connect(addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+            TestClient { writer: stream.try_clone().unwrap(), lines: BufReader::new(stream).lines() }
+        }
+        
+        fn call(&mut self, method: &str, params: Value) -> Value {
+            writeln!(self.writer, "{}", json!({"id": 1, "method": method, "params": params})).unwrap();
+            self.recv()
+        }
+        
+        fn recv(&mut self) -> Value {
+            serde_json::from_str(&self.lines.next().unwrap().unwrap()).unwrap()
+        }
+    }
+    
+    // A nonce for `data` whose hash has at least `min` but fewer than `max`
+    // leading zero bits, as hex for `mining.submit`
+    fn nonce_between(data: &[u8], min: u8, max: u8) -> String {
+        let verifier = PowVerifier::new(data);
+        let nonce = (0..).find(|&nonce| verifier.check(nonce, min) && !verifier.check(nonce, max)).unwrap();
+        format!("{:016x}", nonce)
+    }
+    
+    #[test]
+    fn test_stratum_session() {
+        let (blocks, found) = mpsc::channel();
+        let template = Arc::new(Mutex::new(&b"template one"[..]));
+        let source = TestSource { template: Arc::clone(&template), blocks: Mutex::new(blocks) };
+        let server = Arc::new(Server::bind("127.0.0.1:0", source, 4).unwrap());
+        let runner = Arc::clone(&server);
+        thread::spawn(move || runner.run());
+        let mut client = TestClient::connect(server.local_addr().unwrap());
+        
+        let reply = client.call("mining.submit", json!(["w", "00", "0"]));
+        assert_eq!(reply["error"][0], ERR_NOT_SUBSCRIBED);
+        
+        // Subscribing is followed by the share difficulty and a job
+        let reply = client.call("mining.subscribe", json!([]));
+        let extranonce = u64::from_str_radix(reply["result"][1].as_str().unwrap(), 16).unwrap();
+        let set_difficulty = client.recv();
+        assert_eq!(set_difficulty["method"], "mining.set_difficulty");
+        assert_eq!(set_difficulty["params"], json!([4]));
+        let notify = client.recv();
+        assert_eq!(notify["method"], "mining.notify");
+        
+        let job = server.source.job(extranonce);
+        assert_eq!(notify["params"], json!([job.id, to_hex(&job.data), NETWORK_DIFFICULTY, true]));
+        assert_eq!(client.call("mining.authorize", json!(["w", "x"]))["result"], true);
+        
+        // A share at share difficulty is accepted once, without a block
+        let share = nonce_between(&job.data, 4, NETWORK_DIFFICULTY);
+        assert_eq!(client.call("mining.submit", json!(["w", job.id, share]))["result"], true);
+        assert_eq!(client.call("mining.submit", json!(["w", job.id, share]))["error"][0], ERR_DUPLICATE_SHARE);
+        let weak = nonce_between(&job.data, 0, 4);
+        assert_eq!(client.call("mining.submit", json!(["w", job.id, weak]))["error"][0], ERR_LOW_DIFFICULTY);
+        assert_eq!(client.call("mining.submit", json!(["w", "ff", share]))["error"][0], ERR_JOB_NOT_FOUND);
+        assert!(found.try_recv().is_err());
+        
+        // A new job and difficulty are pushed; the old job still takes shares
+        *template.lock().unwrap() = b"template two";
+        server.notify(true);
+        let notify = client.recv();
+        assert_ne!(notify["params"][0], json!(job.id));
+        assert_eq!(notify["params"][3], true);
+        server.set_difficulty(6);
+        assert_eq!(client.recv()["params"], json!([6]));
+        
+        let solution = nonce_between(&job.data, NETWORK_DIFFICULTY, 128);
+        assert_eq!(client.call("mining.submit", json!(["w", job.id, solution]))["result"], true);
+        let block = found.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!((&block.job_id, block.extranonce), (&job.id, extranonce));
+        assert_eq!(format!("{:016x}", block.nonce), solution);
+        assert!(SMCHash::verify_proof_of_work(&job.data, block.nonce, NETWORK_DIFFICULTY, &block.hash));
+        
+        assert_eq!(client.call("mining.bogus", json!([]))["error"][0], ERR_OTHER);
+    }
+}
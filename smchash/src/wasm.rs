@@ -0,0 +1,69 @@
+//! wasm-bindgen exports, so browser code (e.g. a block explorer) can check
+//! hashes and proofs of work client-side
+//!
+//! Build with `wasm-pack build --features wasm`. Only the single-threaded,
+//! clock-free parts of the crate are exposed; they also build without std.
+//! Hashes cross the boundary as 16-byte `Uint8Array`s, nonces as `BigInt`.
+
+use crate::{ct_eq, hex_to_hash, SMCHash};
+use alloc::{string::String, vec::Vec};
+use wasm_bindgen::prelude::*;
+
+/// `SMCHash::hash` of `data`
+#[wasm_bindgen]
+pub fn hash(data: &[u8]) -> Vec<u8> {
+    SMCHash::hash(data).to_vec()
+}
+
+/// Whether `expected` is the hash of `data`. False if `expected` is not 16
+/// bytes.
+#[wasm_bindgen]
+pub fn verify(data: &[u8], expected: &[u8]) -> bool {
+    to_hash(expected).is_some_and(|expected| ct_eq(&SMCHash::hash(data), &expected))
+}
+
+/// Lowercase hex of a 16-byte hash
+#[wasm_bindgen(js_name = hashToHex)]
+pub fn hash_to_hex(hash: &[u8]) -> Result<String, JsError> {
+    let hash = to_hash(hash).ok_or_else(|| JsError::new("hash must be 16 bytes"))?;
+    Ok(crate::hash_to_hex(&hash))
+}
+
+/// Parses the output of `hashToHex` (an optional `0x` prefix is allowed)
+#[wasm_bindgen(js_name = hexToHash)]
+pub fn hex_to_hash_js(hex: &str) -> Result<Vec<u8>, JsError> {
+    hex_to_hash(hex).map(|hash| hash.to_vec()).map_err(|e| JsError::new(&alloc::format!("{}", e)))
+}
+
+/// `SMCHash::verify_proof_of_work`. False if `expected_hash` is not 16 bytes.
+#[wasm_bindgen(js_name = verifyProofOfWork)]
+pub fn verify_proof_of_work(data: &[u8], nonce: u64, difficulty: u8, expected_hash: &[u8]) -> bool {
+    to_hash(expected_hash).is_some_and(|expected| SMCHash::verify_proof_of_work(data, nonce, difficulty, &expected))
+}
+
+fn to_hash(bytes: &[u8]) -> Option<[u8; 16]> {
+    bytes.try_into().ok()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_wasm_exports() {
+        let data = b"explorer block";
+        let digest = hash(data);
+        assert_eq!(digest, SMCHash::hash(data));
+        assert!(verify(data, &digest));
+        assert!(!verify(b"other", &digest));
+        assert!(!verify(data, &digest[..15]));
+        
+        let hex = hash_to_hex(&digest).unwrap();
+        assert_eq!(hex, crate::hash_to_hex(&SMCHash::hash(data)));
+        assert_eq!(hex_to_hash_js(&hex).unwrap(), digest);
+        
+        let (nonce, pow) = SMCHash::create_proof_of_work(data, 6);
+        assert!(verify_proof_of_work(data, nonce, 6, &pow));
+        assert!(!verify_proof_of_work(data, nonce, 6, &pow[1..]));
+    }
+}